@@ -0,0 +1,27 @@
+extern crate painter;
+
+use std::time::Instant;
+
+use painter::CssParser;
+
+const RULE_SETS: usize = 50_000;
+const ROUNDS: u32 = 5;
+
+// Times the parser on a large generated stylesheet.
+fn main() {
+    let mut css = String::new();
+    for i in 0..RULE_SETS {
+        css.push_str(&format!(
+            ".item-{} > a, #id-{} {{ color: rgb({}, 20, 30); margin: {}px auto; font-family: \"Helvetica Neue\", sans-serif; }}\n",
+            i, i, i % 256, i % 40));
+    }
+
+    println!("parsing {} KiB, {} rounds", css.len() / 1024, ROUNDS);
+
+    let mut parser = CssParser::new();
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        parser.parse_bytes(&css).unwrap();
+    }
+    println!("{:?} per round", start.elapsed() / ROUNDS);
+}
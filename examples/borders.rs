@@ -0,0 +1,20 @@
+extern crate painter;
+
+use std::env;
+
+fn main() {
+    let path = env::args().nth(1).unwrap_or("examples/borders.css".to_string());
+
+    let root = match painter::parse_file(&path) {
+        Ok(root) => root,
+        Err(why) => return eprintln!("couldn't parse {}: {}", path, why),
+    };
+
+    for layout_box in painter::layout(&root, (512.0, 512.0)) {
+        let vertices = painter::border_vertices(&layout_box);
+        println!("{} has {} border quads", layout_box.selectors.join(", "), vertices.len() / 6);
+        for vertex in vertices.iter() {
+            println!("{:?}", vertex);
+        }
+    }
+}
@@ -0,0 +1,35 @@
+extern crate painter;
+
+use std::env;
+
+use painter::Rect;
+
+const VIEWPORT: (f32, f32) = (800.0, 600.0);
+
+// Runs a stylesheet through parse, layout and paint. Without a path it lays out
+// two_column.css and checks that the columns split the viewport 30/70.
+fn main() {
+    let path = env::args().nth(1);
+    let file = path.clone().unwrap_or("examples/two_column.css".to_string());
+
+    let root = match painter::parse_file(&file) {
+        Ok(root) => root,
+        Err(why) => return eprintln!("couldn't parse {}: {}", file, why),
+    };
+
+    let boxes = painter::layout(&root, VIEWPORT);
+    for layout_box in boxes.iter() {
+        println!("{}: {:?}", layout_box.selectors.join(", "), layout_box.border);
+    }
+
+    let vertices = painter::paint_boxes(&boxes);
+    println!("clear color {:?}", painter::clear_color(&root));
+    println!("{} vertices", vertices.len());
+
+    if path.is_none() {
+        assert_eq!(boxes.len(), 2);
+        assert_eq!(boxes[0].border, Rect::new(0.0, 0.0, 240.0, 600.0));
+        assert_eq!(boxes[1].border, Rect::new(240.0, 0.0, 560.0, 600.0));
+        println!("two columns laid out as expected");
+    }
+}
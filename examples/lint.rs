@@ -0,0 +1,27 @@
+extern crate painter;
+
+use std::env;
+
+// Prints a warning for every property that is accidentally declared twice.
+fn main() {
+    let path = env::args().nth(1).unwrap_or_else(|| "example.css".to_string());
+
+    let root = match painter::parse_file(&path) {
+        Ok(root) => root,
+        Err(why) => {
+            eprintln!("couldn't parse {}: {}", path, why);
+            return;
+        }
+    };
+
+    let mut warnings = 0;
+    let media_rule_sets = root.media_queries.iter().flat_map(|m| m.rule_sets.iter());
+    for rule_set in root.rule_sets.iter().chain(media_rule_sets) {
+        for property in rule_set.duplicate_properties() {
+            println!("warning: `{}` is declared more than once in `{}`", property, rule_set.selectors.join(", "));
+            warnings += 1;
+        }
+    }
+
+    println!("{}: {} warning(s)", path, warnings);
+}
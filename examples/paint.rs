@@ -0,0 +1,19 @@
+extern crate painter;
+
+use std::env;
+
+fn main() {
+    let path = env::args().nth(1).unwrap_or("examples/boxes.css".to_string());
+
+    let root = match painter::parse_file(&path) {
+        Ok(root) => root,
+        Err(why) => return eprintln!("couldn't parse {}: {}", path, why),
+    };
+
+    let vertices = painter::paint(&root, (512.0, 512.0));
+    println!("clear color {:?}", painter::clear_color(&root));
+    println!("{} boxes", vertices.len() / 6);
+    for vertex in vertices.iter() {
+        println!("{:?}", vertex);
+    }
+}
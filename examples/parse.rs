@@ -0,0 +1,10 @@
+extern crate painter;
+
+fn main() {
+    let path = "example.css";
+
+    match painter::parse_file(path) {
+        Ok(root) => println!("{:?}", root),
+        Err(why) => eprintln!("couldn't parse {}: {}", path, why),
+    }
+}
@@ -0,0 +1,29 @@
+extern crate painter;
+
+use std::env;
+use std::io::{self, BufRead};
+
+use painter::LiveStylesheet;
+
+// Type `r` and enter to re-read the stylesheet after editing it.
+fn main() {
+    let path = env::args().nth(1).unwrap_or("example.css".to_string());
+
+    let mut stylesheet = match LiveStylesheet::load(&path, (512.0, 512.0)) {
+        Ok(stylesheet) => stylesheet,
+        Err(why) => return eprintln!("couldn't parse {}: {}", path, why),
+    };
+    println!("{} boxes", stylesheet.vertices().len() / 6);
+
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        if line.map(|line| line.trim() != "r").unwrap_or(true) {
+            continue;
+        }
+
+        match stylesheet.reload() {
+            Ok(()) => println!("{} boxes", stylesheet.vertices().len() / 6),
+            Err(why) => eprintln!("couldn't reload {}, keeping the previous styles: {}", path, why),
+        }
+    }
+}
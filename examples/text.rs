@@ -0,0 +1,24 @@
+extern crate image;
+extern crate painter;
+
+use std::env;
+
+use image::RgbaImage;
+use painter::FontAtlas;
+
+// Pass the path of an 8x8 glyph atlas to use it, otherwise a blank one is made up.
+fn main() {
+    let atlas = match env::args().nth(1) {
+        Some(path) => match FontAtlas::open(&path, 8, 8) {
+            Ok(atlas) => atlas,
+            Err(why) => return eprintln!("couldn't open {}: {}", path, why),
+        },
+        None => FontAtlas::new(RgbaImage::new(16 * 8, 6 * 8), 8, 8).unwrap(),
+    };
+
+    let vertices = painter::text_vertices(&atlas, "Hello", 16.0, 16.0, [1.0, 1.0, 1.0, 1.0]);
+    println!("{} glyphs", vertices.len() / 6);
+    for vertex in vertices.iter() {
+        println!("{:?}", vertex);
+    }
+}
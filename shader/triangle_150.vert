@@ -3,10 +3,10 @@
 uniform mat4 u_ModelViewProj;
 
 in vec2 a_Pos;
-in vec3 a_Color;
+in vec4 a_Color;
 out vec4 v_Color;
 
 void main() {
-    v_Color = vec4(a_Color, 1.0);
-    gl_Position = vec4(a_Pos, 0.0, 1.0)*u_ModelViewProj;
+    v_Color = a_Color;
+    gl_Position = u_ModelViewProj * vec4(a_Pos, 0.0, 1.0);
 }
@@ -0,0 +1,200 @@
+use length::{Length, parse_length};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// The expression of a `calc()`, with `*` and `/` binding tighter than `+` and `-`.
+// Parsing checks the types, so lengths are only added to lengths and never multiplied
+// together or divided by.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum CalcExpr {
+    Length(Length),
+    Number(f32),
+    Add(Box<CalcExpr>, Box<CalcExpr>),
+    Sub(Box<CalcExpr>, Box<CalcExpr>),
+    Mul(Box<CalcExpr>, Box<CalcExpr>),
+    Div(Box<CalcExpr>, Box<CalcExpr>),
+}
+impl CalcExpr {
+    // With lengths turned into pixels by `px`, which is where percentages get their
+    // basis. None when a length can't be resolved or a division is by zero.
+    pub fn eval<F: Fn(Length) -> Option<f32>>(&self, px : &F) -> Option<f32> {
+        return match *self {
+            CalcExpr::Length(length) => px(length),
+            CalcExpr::Number(number) => Some(number),
+            CalcExpr::Add(ref a, ref b) => Some(a.eval(px)? + b.eval(px)?),
+            CalcExpr::Sub(ref a, ref b) => Some(a.eval(px)? - b.eval(px)?),
+            CalcExpr::Mul(ref a, ref b) => Some(a.eval(px)? * b.eval(px)?),
+            CalcExpr::Div(ref a, ref b) => {
+                let divisor = b.eval(px)?;
+                if divisor == 0.0 { None } else { Some(a.eval(px)? / divisor) }
+            },
+        }
+    }
+
+    // Whether the expression evaluates to a length rather than a plain number.
+    pub fn is_length(&self) -> bool {
+        return match *self {
+            CalcExpr::Length(_) => true,
+            CalcExpr::Number(_) => false,
+            CalcExpr::Add(ref a, _) | CalcExpr::Sub(ref a, _) | CalcExpr::Div(ref a, _) => a.is_length(),
+            CalcExpr::Mul(ref a, ref b) => a.is_length() || b.is_length(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Leaf(CalcExpr),
+    Operator(char),
+    Open,
+    Close,
+}
+
+// Reads a whole `calc(100% - 20px)` value. Like in CSS, `+` and `-` need whitespace on
+// both sides, and parentheses or a nested `calc(` group.
+pub fn parse_calc(expr : &str) -> Option<CalcExpr> {
+    let expr = expr.trim();
+    if !is_calc(expr) {
+        return None;
+    }
+
+    let tokens = tokenize(expr)?;
+    return match primary(&tokens)? {
+        (calc, []) => Some(calc),
+        _ => None
+    }
+}
+
+fn is_calc(text : &str) -> bool {
+    return text.get(..5).is_some_and(|start| start.eq_ignore_ascii_case("calc("));
+}
+
+fn tokenize(expr : &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = expr;
+
+    loop {
+        let trimmed = rest.trim_start();
+        let spaced_before = trimmed.len() < rest.len();
+        rest = trimmed;
+        let c = match rest.chars().next() {
+            Some(c) => c,
+            None => return Some(tokens)
+        };
+        let after = &rest[c.len_utf8()..];
+
+        if is_calc(rest) {
+            tokens.push(Token::Open);
+            rest = &rest[5..];
+            continue;
+        }
+        let token = match c {
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '*' | '/' => Token::Operator(c),
+            '+' | '-' if spaced_before && after.starts_with(char::is_whitespace) => Token::Operator(c),
+            _ => {
+                let end = rest.find(|c : char| c.is_whitespace() || "()*/".contains(c)).unwrap_or(rest.len());
+                tokens.push(Token::Leaf(leaf(&rest[..end])?));
+                rest = &rest[end..];
+                continue;
+            }
+        };
+        tokens.push(token);
+        rest = after;
+    }
+}
+
+fn leaf(word : &str) -> Option<CalcExpr> {
+    // Keeps words like `inf` and `auto` out, which would parse otherwise.
+    if !word.starts_with(|c : char| c.is_ascii_digit() || c == '.' || c == '+' || c == '-') {
+        return None;
+    }
+    if let Ok(number) = word.parse::<f32>() {
+        return Some(CalcExpr::Number(number));
+    }
+    return parse_length(word).map(CalcExpr::Length);
+}
+
+fn sum(tokens : &[Token]) -> Option<(CalcExpr, &[Token])> {
+    let (mut left, mut rest) = product(tokens)?;
+    loop {
+        let op = match rest.first() {
+            Some(&Token::Operator(op)) if op == '+' || op == '-' => op,
+            _ => return Some((left, rest))
+        };
+        let (right, after) = product(&rest[1..])?;
+        if left.is_length() != right.is_length() {
+            return None;
+        }
+        let (left_box, right_box) = (Box::new(left), Box::new(right));
+        left = if op == '+' { CalcExpr::Add(left_box, right_box) } else { CalcExpr::Sub(left_box, right_box) };
+        rest = after;
+    }
+}
+
+fn product(tokens : &[Token]) -> Option<(CalcExpr, &[Token])> {
+    let (mut left, mut rest) = primary(tokens)?;
+    loop {
+        let op = match rest.first() {
+            Some(&Token::Operator(op)) if op == '*' || op == '/' => op,
+            _ => return Some((left, rest))
+        };
+        let (right, after) = primary(&rest[1..])?;
+        if right.is_length() && (op == '/' || left.is_length()) {
+            return None;
+        }
+        let (left_box, right_box) = (Box::new(left), Box::new(right));
+        left = if op == '*' { CalcExpr::Mul(left_box, right_box) } else { CalcExpr::Div(left_box, right_box) };
+        rest = after;
+    }
+}
+
+fn primary(tokens : &[Token]) -> Option<(CalcExpr, &[Token])> {
+    return match *tokens.first()? {
+        Token::Leaf(ref leaf) => Some((leaf.clone(), &tokens[1..])),
+        Token::Open => match sum(&tokens[1..])? {
+            (calc, rest) if rest.first() == Some(&Token::Close) => Some((calc, &rest[1..])),
+            _ => None
+        },
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Resolves percentages against an 800px container.
+    fn at_800(calc : &CalcExpr) -> Option<f32> {
+        return calc.eval(&|length| match length {
+            Length::Px(value) => Some(value),
+            Length::Percent(percent) => Some(percent * 8.0),
+            _ => None
+        });
+    }
+
+    #[test]
+    fn evaluates_expressions() {
+        let calc = parse_calc("calc(100% - 20px)").unwrap();
+        assert_eq!(calc, CalcExpr::Sub(Box::new(CalcExpr::Length(Length::Percent(100.0))), Box::new(CalcExpr::Length(Length::Px(20.0)))));
+        assert_eq!(at_800(&calc), Some(780.0));
+        assert_eq!(at_800(&parse_calc("calc(10px + 2 * 5px)").unwrap()), Some(20.0));
+        assert_eq!(at_800(&parse_calc("CALC((10px + 2px) * 2)").unwrap()), Some(24.0));
+        assert_eq!(at_800(&parse_calc("calc(50%/2 - calc(-10px + 5px))").unwrap()), Some(205.0));
+        assert_eq!(at_800(&parse_calc("calc(10px / 0)").unwrap()), None);
+        assert!(!parse_calc("calc(2 * 3)").unwrap().is_length());
+    }
+
+    #[test]
+    fn rejects_malformed_expressions() {
+        let malformed = ["calc(100%-20px)", "calc(1px + 2)", "calc(1px * 2px)", "calc(2 / 1px)", "calc((1px)", "calc(1px))",
+            "calc()", "calc(1px +)", "calc(auto)", "calc(1px 2px)", "100px", "calc(1px) + calc(2px)"];
+        for expr in malformed.iter() {
+            assert_eq!(parse_calc(expr), None, "{}", expr);
+        }
+    }
+}
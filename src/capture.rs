@@ -0,0 +1,71 @@
+use std::path::Path;
+
+use image::{ImageResult, RgbaImage};
+
+// Builds an image from RGBA pixels read back from the color target. GL hands rows
+// back bottom-up and each row may be padded to `row_stride` bytes, so rows are
+// flipped and trimmed to `width * 4` bytes. Gives `None` when the stride is shorter than
+// a row or `pixels` ends before the last one.
+pub fn capture_frame(pixels : &[u8], width : u32, height : u32, row_stride : usize) -> Option<RgbaImage> {
+    let row_len = width as usize * 4;
+    if row_stride < row_len {
+        return None;
+    }
+    if height > 0 && pixels.len() < (height as usize - 1) * row_stride + row_len {
+        return None;
+    }
+
+    let mut data = Vec::with_capacity(row_len * height as usize);
+
+    for row in (0..height as usize).rev() {
+        let start = row * row_stride;
+        data.extend_from_slice(&pixels[start..start + row_len]);
+    }
+
+    return RgbaImage::from_raw(width, height, data);
+}
+
+pub fn save_screenshot<P: AsRef<Path>>(frame : &RgbaImage, path : P) -> ImageResult<()> {
+    return frame.save(path);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use offscreen::render_offscreen;
+
+    #[test]
+    fn flips_and_trims_rows() {
+        // Two rows of one pixel each, padded to eight bytes, bottom row first.
+        let pixels = [1, 2, 3, 4, 0, 0, 0, 0, 5, 6, 7, 8, 0, 0, 0, 0];
+        let frame = capture_frame(&pixels, 1, 2, 8).unwrap();
+        assert_eq!(frame.into_raw(), vec![5, 6, 7, 8, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn saves_a_png() {
+        let frame = capture_frame(&[1, 2, 3, 255, 5, 6, 7, 255], 2, 1, 8).unwrap();
+        let name = format!("painter_{}_saves_a_png.png", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        save_screenshot(&frame, &path).unwrap();
+        assert_eq!(image::open(&path).unwrap().to_rgba8(), frame);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_short_frames() {
+        assert_eq!(capture_frame(&[0; 16], 2, 2, 4), None);
+        assert_eq!(capture_frame(&[0; 15], 2, 2, 8), None);
+        assert!(capture_frame(&[0; 12], 1, 2, 8).is_some());
+    }
+
+    #[test]
+    fn captures_an_offscreen_render() {
+        let root = ::parse_str("a { width: 10px; height: 10px; background-color: red }").unwrap();
+        let mut pixels = render_offscreen(&root, 10, 10);
+        // Hand the rows over bottom-up, as they would be read back from the target.
+        pixels = pixels.chunks(10 * 4).rev().flatten().cloned().collect();
+        let frame = capture_frame(&pixels, 10, 10, 10 * 4).unwrap();
+        assert_eq!(frame.get_pixel(5, 5).0, [0xff, 0x00, 0x00, 0xff]);
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use parse::{CssRoot, CssRuleSet};
+use selector::{Specificity, specificity};
+
+// Selector matching is exact string equality against each grouped selector for now.
+pub fn computed(root : &CssRoot, selector : &str) -> HashMap<String, String> {
+    return computed_with_hover(root, selector, false);
+}
+
+// As `computed`, but while `hovered` the rule sets for `selector:hover` apply as well.
+pub fn computed_with_hover(root : &CssRoot, selector : &str, hovered : bool) -> HashMap<String, String> {
+    let mut matched: Vec<_> = root.select(selector).into_iter()
+        .map(|rule_set| (specificity(selector), rule_set))
+        .collect();
+
+    if hovered {
+        let hover = hover_selector(selector);
+        matched.extend(root.select(&hover).into_iter().map(|rule_set| (specificity(&hover), rule_set)));
+    }
+
+    return cascade(matched);
+}
+
+// The style of an element known by its tag, classes and id. Rule sets apply when one
+// of their selectors is a single simple selector, like `p`, `.note`, `#main` or `*`,
+// naming one of them.
+pub fn compute_for_classes(root : &CssRoot, tag : &str, classes : &[&str], id : Option<&str>) -> HashMap<String, String> {
+    let targets = |selector : &str| {
+        return match (selector.strip_prefix('.'), selector.strip_prefix('#')) {
+            (Some(class), _) => classes.contains(&class),
+            (_, Some(name)) => id == Some(name),
+            _ => selector == "*" || selector.eq_ignore_ascii_case(tag)
+        }
+    };
+
+    let matched = root.rule_sets.iter()
+        .filter_map(|rule_set| {
+            let best = rule_set.selectors.iter()
+                .filter(|selector| is_simple(selector) && targets(selector))
+                .map(|selector| specificity(selector))
+                .max()?;
+            Some((best, rule_set))
+        })
+        .collect();
+
+    return cascade(matched);
+}
+
+fn is_simple(selector : &str) -> bool {
+    let name = selector.strip_prefix(['.', '#']).unwrap_or(selector);
+    return selector == "*" || (!name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_'));
+}
+
+pub(crate) fn hover_selector(selector : &str) -> String {
+    return format!("{}:hover", selector);
+}
+
+// Appends the declarations of every matching `:hover` rule set, so they win `get`.
+pub(crate) fn with_hover(root : &CssRoot, rule_set : &CssRuleSet) -> CssRuleSet {
+    let mut hovered = rule_set.clone();
+    for selector in rule_set.selectors.iter() {
+        for hover in root.select(&hover_selector(selector)) {
+            hovered.rules.extend(hover.rules.iter().cloned());
+        }
+    }
+    return hovered;
+}
+
+// Rule sets like `.button:hover` only style another box and never make one of their own.
+pub(crate) fn is_hover_only(rule_set : &CssRuleSet) -> bool {
+    return rule_set.selectors.iter().all(|selector| selector.ends_with(":hover"));
+}
+
+// Merges matched rule sets given in source order. Declarations are ranked by
+// importance then specificity; the stable sort keeps later sources winning ties.
+fn cascade(matched : Vec<(Specificity, &CssRuleSet)>) -> HashMap<String, String> {
+    let mut declarations: Vec<_> = matched.iter()
+        .flat_map(|&(specificity, rule_set)| {
+            rule_set.rules.iter().map(move |rule| (rule.important, specificity, rule))
+        })
+        .collect();
+    declarations.sort_by_key(|&(important, specificity, _)| (important, specificity));
+
+    let mut style = HashMap::new();
+    for (_, _, rule) in declarations {
+        style.insert(rule.key.clone(), rule.value.clone());
+    }
+
+    return style;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn higher_specificity_wins() {
+        let root = ::parse_str("p { color: red } .x, p { color: blue } #y, p.z { color: green }").unwrap();
+        assert_eq!(computed(&root, "p")["color"], "blue");
+        assert_eq!(computed(&root, "p.z")["color"], "green");
+    }
+
+    #[test]
+    fn important_wins_over_later_rules() {
+        let root = ::parse_str("p { margin: 0 } p { margin: 1px !important } p { margin: 2px }").unwrap();
+        let style = computed(&root, "p");
+        assert_eq!(style["margin"], "1px");
+        assert_eq!(style.len(), 1);
+        assert!(computed(&root, "q").is_empty());
+    }
+
+    #[test]
+    fn computed_with_unclosed_selector() {
+        let root = ::parse_str("a { color: red }").unwrap();
+        assert!(computed(&root, "a:not(").is_empty());
+    }
+
+    #[test]
+    fn computes_for_classes() {
+        let root = ::parse_str("#main { color: blue; margin: 1px; } .note { color: red !important; margin: 2px; padding: 3px } \
+            p { padding: 4px; border: none } .x, p.note { border: solid } * { top: 0 } .note .y { left: 1px }").unwrap();
+        let style = compute_for_classes(&root, "P", &["note", "other"], Some("main"));
+        assert_eq!(style["color"], "red");
+        assert_eq!(style["margin"], "1px");
+        assert_eq!(style["padding"], "3px");
+        assert_eq!(style["border"], "none");
+        assert_eq!(style["top"], "0");
+        assert!(!style.contains_key("left"));
+
+        let style = compute_for_classes(&root, "div", &["x"], None);
+        assert_eq!(style["border"], "solid");
+        assert_eq!(style.len(), 2);
+    }
+}
@@ -0,0 +1,382 @@
+use std::iter;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+impl Color {
+    pub fn new(r : u8, g : u8, b : u8, a : u8) -> Color {
+        return Color {r, g, b, a}
+    }
+
+    pub fn to_rgba_f32(&self) -> [f32; 4] {
+        return [
+            self.r as f32 / 255.0,
+            self.g as f32 / 255.0,
+            self.b as f32 / 255.0,
+            self.a as f32 / 255.0,
+        ];
+    }
+}
+
+pub fn parse_color(s : &str) -> Option<Color> {
+    let s = s.trim();
+
+    if let Some(hex) = s.strip_prefix('#') {
+        return parse_hex_color(hex);
+    }
+
+    if let Some(args) = function_args(s, "rgb") {
+        return parse_rgb_color(&args, false);
+    }
+
+    if let Some(args) = function_args(s, "rgba") {
+        return parse_rgb_color(&args, true);
+    }
+
+    if let Some(args) = function_args(s, "hsl") {
+        return parse_hsl_color(&args, false);
+    }
+
+    if let Some(args) = function_args(s, "hsla") {
+        return parse_hsl_color(&args, true);
+    }
+
+    return named_color(s);
+}
+
+pub fn named_color(name : &str) -> Option<Color> {
+    let name = name.to_ascii_lowercase();
+    if name == "transparent" {
+        return Some(Color::new(0, 0, 0, 0));
+    }
+
+    return NAMED_COLORS.binary_search_by_key(&name.as_str(), |&(n, _, _, _)| n).ok()
+        .map(|i| {
+            let (_, r, g, b) = NAMED_COLORS[i];
+            Color::new(r, g, b, 255)
+        });
+}
+
+fn function_args<'a>(s : &'a str, name : &str) -> Option<Vec<&'a str>> {
+    let open = s.find('(')?;
+    if !s[..open].trim().eq_ignore_ascii_case(name) || !s.ends_with(')') {
+        return None;
+    }
+
+    let args = &s[open + 1..s.len() - 1];
+    return Some(args.split(',').map(|arg| arg.trim()).collect());
+}
+
+fn parse_hex_color(hex : &str) -> Option<Color> {
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let expanded: String = match hex.len() {
+        3 => hex.chars().flat_map(|c| iter::repeat_n(c, 2)).chain("ff".chars()).collect(),
+        6 => format!("{}ff", hex),
+        8 => hex.to_string(),
+        _ => return None
+    };
+
+    let channel = |i : usize| u8::from_str_radix(&expanded[i..i + 2], 16).ok();
+    return Some(Color::new(channel(0)?, channel(2)?, channel(4)?, channel(6)?));
+}
+
+fn parse_rgb_color(args : &[&str], has_alpha : bool) -> Option<Color> {
+    let expected = if has_alpha { 4 } else { 3 };
+    if args.len() != expected {
+        return None;
+    }
+
+    let r = parse_rgb_channel(args[0])?;
+    let g = parse_rgb_channel(args[1])?;
+    let b = parse_rgb_channel(args[2])?;
+    let a = if has_alpha { parse_alpha(args[3])? } else { 255 };
+
+    return Some(Color::new(r, g, b, a));
+}
+
+fn parse_rgb_channel(arg : &str) -> Option<u8> {
+    let value = match arg.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f32>().ok()? * 2.55,
+        None => arg.parse::<f32>().ok()?
+    };
+
+    return to_channel(value);
+}
+
+fn parse_hsl_color(args : &[&str], has_alpha : bool) -> Option<Color> {
+    let expected = if has_alpha { 4 } else { 3 };
+    if args.len() != expected {
+        return None;
+    }
+
+    let hue = args[0].trim_end_matches("deg").parse::<f32>().ok()?;
+    let saturation = parse_percentage(args[1])?;
+    let lightness = parse_percentage(args[2])?;
+    let a = if has_alpha { parse_alpha(args[3])? } else { 255 };
+
+    let (r, g, b) = hsl_to_rgb(hue.rem_euclid(360.0), saturation, lightness);
+    return Some(Color::new(to_channel(r * 255.0)?, to_channel(g * 255.0)?, to_channel(b * 255.0)?, a));
+}
+
+fn parse_percentage(arg : &str) -> Option<f32> {
+    let value = arg.strip_suffix('%')?.trim().parse::<f32>().ok()?;
+    if !(0.0..=100.0).contains(&value) {
+        return None;
+    }
+
+    return Some(value / 100.0);
+}
+
+fn hsl_to_rgb(hue : f32, saturation : f32, lightness : f32) -> (f32, f32, f32) {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let sector = hue / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x)
+    };
+
+    let m = lightness - chroma / 2.0;
+    return (r + m, g + m, b + m);
+}
+
+fn parse_alpha(arg : &str) -> Option<u8> {
+    let alpha = arg.parse::<f32>().ok()?;
+    return to_channel(alpha * 255.0);
+}
+
+fn to_channel(value : f32) -> Option<u8> {
+    if !(0.0..=255.0).contains(&value) {
+        return None;
+    }
+
+    return Some(value.round() as u8);
+}
+
+// Sorted by name so named_color can binary search.
+const NAMED_COLORS: &[(&str, u8, u8, u8)] = &[
+    ("aliceblue", 0xf0, 0xf8, 0xff),
+    ("antiquewhite", 0xfa, 0xeb, 0xd7),
+    ("aqua", 0x00, 0xff, 0xff),
+    ("aquamarine", 0x7f, 0xff, 0xd4),
+    ("azure", 0xf0, 0xff, 0xff),
+    ("beige", 0xf5, 0xf5, 0xdc),
+    ("bisque", 0xff, 0xe4, 0xc4),
+    ("black", 0x00, 0x00, 0x00),
+    ("blanchedalmond", 0xff, 0xeb, 0xcd),
+    ("blue", 0x00, 0x00, 0xff),
+    ("blueviolet", 0x8a, 0x2b, 0xe2),
+    ("brown", 0xa5, 0x2a, 0x2a),
+    ("burlywood", 0xde, 0xb8, 0x87),
+    ("cadetblue", 0x5f, 0x9e, 0xa0),
+    ("chartreuse", 0x7f, 0xff, 0x00),
+    ("chocolate", 0xd2, 0x69, 0x1e),
+    ("coral", 0xff, 0x7f, 0x50),
+    ("cornflowerblue", 0x64, 0x95, 0xed),
+    ("cornsilk", 0xff, 0xf8, 0xdc),
+    ("crimson", 0xdc, 0x14, 0x3c),
+    ("cyan", 0x00, 0xff, 0xff),
+    ("darkblue", 0x00, 0x00, 0x8b),
+    ("darkcyan", 0x00, 0x8b, 0x8b),
+    ("darkgoldenrod", 0xb8, 0x86, 0x0b),
+    ("darkgray", 0xa9, 0xa9, 0xa9),
+    ("darkgreen", 0x00, 0x64, 0x00),
+    ("darkgrey", 0xa9, 0xa9, 0xa9),
+    ("darkkhaki", 0xbd, 0xb7, 0x6b),
+    ("darkmagenta", 0x8b, 0x00, 0x8b),
+    ("darkolivegreen", 0x55, 0x6b, 0x2f),
+    ("darkorange", 0xff, 0x8c, 0x00),
+    ("darkorchid", 0x99, 0x32, 0xcc),
+    ("darkred", 0x8b, 0x00, 0x00),
+    ("darksalmon", 0xe9, 0x96, 0x7a),
+    ("darkseagreen", 0x8f, 0xbc, 0x8f),
+    ("darkslateblue", 0x48, 0x3d, 0x8b),
+    ("darkslategray", 0x2f, 0x4f, 0x4f),
+    ("darkslategrey", 0x2f, 0x4f, 0x4f),
+    ("darkturquoise", 0x00, 0xce, 0xd1),
+    ("darkviolet", 0x94, 0x00, 0xd3),
+    ("deeppink", 0xff, 0x14, 0x93),
+    ("deepskyblue", 0x00, 0xbf, 0xff),
+    ("dimgray", 0x69, 0x69, 0x69),
+    ("dimgrey", 0x69, 0x69, 0x69),
+    ("dodgerblue", 0x1e, 0x90, 0xff),
+    ("firebrick", 0xb2, 0x22, 0x22),
+    ("floralwhite", 0xff, 0xfa, 0xf0),
+    ("forestgreen", 0x22, 0x8b, 0x22),
+    ("fuchsia", 0xff, 0x00, 0xff),
+    ("gainsboro", 0xdc, 0xdc, 0xdc),
+    ("ghostwhite", 0xf8, 0xf8, 0xff),
+    ("gold", 0xff, 0xd7, 0x00),
+    ("goldenrod", 0xda, 0xa5, 0x20),
+    ("gray", 0x80, 0x80, 0x80),
+    ("green", 0x00, 0x80, 0x00),
+    ("greenyellow", 0xad, 0xff, 0x2f),
+    ("grey", 0x80, 0x80, 0x80),
+    ("honeydew", 0xf0, 0xff, 0xf0),
+    ("hotpink", 0xff, 0x69, 0xb4),
+    ("indianred", 0xcd, 0x5c, 0x5c),
+    ("indigo", 0x4b, 0x00, 0x82),
+    ("ivory", 0xff, 0xff, 0xf0),
+    ("khaki", 0xf0, 0xe6, 0x8c),
+    ("lavender", 0xe6, 0xe6, 0xfa),
+    ("lavenderblush", 0xff, 0xf0, 0xf5),
+    ("lawngreen", 0x7c, 0xfc, 0x00),
+    ("lemonchiffon", 0xff, 0xfa, 0xcd),
+    ("lightblue", 0xad, 0xd8, 0xe6),
+    ("lightcoral", 0xf0, 0x80, 0x80),
+    ("lightcyan", 0xe0, 0xff, 0xff),
+    ("lightgoldenrodyellow", 0xfa, 0xfa, 0xd2),
+    ("lightgray", 0xd3, 0xd3, 0xd3),
+    ("lightgreen", 0x90, 0xee, 0x90),
+    ("lightgrey", 0xd3, 0xd3, 0xd3),
+    ("lightpink", 0xff, 0xb6, 0xc1),
+    ("lightsalmon", 0xff, 0xa0, 0x7a),
+    ("lightseagreen", 0x20, 0xb2, 0xaa),
+    ("lightskyblue", 0x87, 0xce, 0xfa),
+    ("lightslategray", 0x77, 0x88, 0x99),
+    ("lightslategrey", 0x77, 0x88, 0x99),
+    ("lightsteelblue", 0xb0, 0xc4, 0xde),
+    ("lightyellow", 0xff, 0xff, 0xe0),
+    ("lime", 0x00, 0xff, 0x00),
+    ("limegreen", 0x32, 0xcd, 0x32),
+    ("linen", 0xfa, 0xf0, 0xe6),
+    ("magenta", 0xff, 0x00, 0xff),
+    ("maroon", 0x80, 0x00, 0x00),
+    ("mediumaquamarine", 0x66, 0xcd, 0xaa),
+    ("mediumblue", 0x00, 0x00, 0xcd),
+    ("mediumorchid", 0xba, 0x55, 0xd3),
+    ("mediumpurple", 0x93, 0x70, 0xdb),
+    ("mediumseagreen", 0x3c, 0xb3, 0x71),
+    ("mediumslateblue", 0x7b, 0x68, 0xee),
+    ("mediumspringgreen", 0x00, 0xfa, 0x9a),
+    ("mediumturquoise", 0x48, 0xd1, 0xcc),
+    ("mediumvioletred", 0xc7, 0x15, 0x85),
+    ("midnightblue", 0x19, 0x19, 0x70),
+    ("mintcream", 0xf5, 0xff, 0xfa),
+    ("mistyrose", 0xff, 0xe4, 0xe1),
+    ("moccasin", 0xff, 0xe4, 0xb5),
+    ("navajowhite", 0xff, 0xde, 0xad),
+    ("navy", 0x00, 0x00, 0x80),
+    ("oldlace", 0xfd, 0xf5, 0xe6),
+    ("olive", 0x80, 0x80, 0x00),
+    ("olivedrab", 0x6b, 0x8e, 0x23),
+    ("orange", 0xff, 0xa5, 0x00),
+    ("orangered", 0xff, 0x45, 0x00),
+    ("orchid", 0xda, 0x70, 0xd6),
+    ("palegoldenrod", 0xee, 0xe8, 0xaa),
+    ("palegreen", 0x98, 0xfb, 0x98),
+    ("paleturquoise", 0xaf, 0xee, 0xee),
+    ("palevioletred", 0xdb, 0x70, 0x93),
+    ("papayawhip", 0xff, 0xef, 0xd5),
+    ("peachpuff", 0xff, 0xda, 0xb9),
+    ("peru", 0xcd, 0x85, 0x3f),
+    ("pink", 0xff, 0xc0, 0xcb),
+    ("plum", 0xdd, 0xa0, 0xdd),
+    ("powderblue", 0xb0, 0xe0, 0xe6),
+    ("purple", 0x80, 0x00, 0x80),
+    ("rebeccapurple", 0x66, 0x33, 0x99),
+    ("red", 0xff, 0x00, 0x00),
+    ("rosybrown", 0xbc, 0x8f, 0x8f),
+    ("royalblue", 0x41, 0x69, 0xe1),
+    ("saddlebrown", 0x8b, 0x45, 0x13),
+    ("salmon", 0xfa, 0x80, 0x72),
+    ("sandybrown", 0xf4, 0xa4, 0x60),
+    ("seagreen", 0x2e, 0x8b, 0x57),
+    ("seashell", 0xff, 0xf5, 0xee),
+    ("sienna", 0xa0, 0x52, 0x2d),
+    ("silver", 0xc0, 0xc0, 0xc0),
+    ("skyblue", 0x87, 0xce, 0xeb),
+    ("slateblue", 0x6a, 0x5a, 0xcd),
+    ("slategray", 0x70, 0x80, 0x90),
+    ("slategrey", 0x70, 0x80, 0x90),
+    ("snow", 0xff, 0xfa, 0xfa),
+    ("springgreen", 0x00, 0xff, 0x7f),
+    ("steelblue", 0x46, 0x82, 0xb4),
+    ("tan", 0xd2, 0xb4, 0x8c),
+    ("teal", 0x00, 0x80, 0x80),
+    ("thistle", 0xd8, 0xbf, 0xd8),
+    ("tomato", 0xff, 0x63, 0x47),
+    ("turquoise", 0x40, 0xe0, 0xd0),
+    ("violet", 0xee, 0x82, 0xee),
+    ("wheat", 0xf5, 0xde, 0xb3),
+    ("white", 0xff, 0xff, 0xff),
+    ("whitesmoke", 0xf5, 0xf5, 0xf5),
+    ("yellow", 0xff, 0xff, 0x00),
+    ("yellowgreen", 0x9a, 0xcd, 0x32),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgb(r : u8, g : u8, b : u8) -> Option<Color> {
+        return Some(Color::new(r, g, b, 255));
+    }
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(parse_color("#abc"), rgb(0xaa, 0xbb, 0xcc));
+        assert_eq!(parse_color("#aabbcc"), rgb(0xaa, 0xbb, 0xcc));
+        assert_eq!(parse_color("#11223344"), Some(Color::new(0x11, 0x22, 0x33, 0x44)));
+        for malformed in ["#xyz", "#12", "#12345", "abc", "#", "#+1+"].iter() {
+            assert_eq!(parse_color(malformed), None, "{}", malformed);
+        }
+    }
+
+    #[test]
+    fn parses_rgb_functions() {
+        assert_eq!(parse_color("rgb(255, 0, 0)"), rgb(255, 0, 0));
+        assert_eq!(parse_color("rgb(  255 ,0,   0 )"), rgb(255, 0, 0));
+        assert_eq!(parse_color("rgba(255, 0, 0, 0.5)"), Some(Color::new(255, 0, 0, 128)));
+        assert_eq!(parse_color("rgb(100%, 0%, 50%)"), rgb(255, 0, 128));
+        for malformed in ["rgb(1,2)", "rgb(256,0,0)", "rgba(1,2,3)", "rgba(1,2,3,1.5)", "rgb(1,2,3", "rgb(-1,0,0)"].iter() {
+            assert_eq!(parse_color(malformed), None, "{}", malformed);
+        }
+    }
+
+    #[test]
+    fn parses_hsl_functions() {
+        assert_eq!(parse_color("hsl(0,100%,50%)"), rgb(255, 0, 0));
+        assert_eq!(parse_color("hsl(120, 100%, 50%)"), rgb(0, 255, 0));
+        assert_eq!(parse_color("hsl(-240, 100%, 50%)"), rgb(0, 255, 0));
+        assert_eq!(parse_color("hsla(0, 0%, 100%, 0)"), Some(Color::new(255, 255, 255, 0)));
+        for malformed in ["hsl(0,100,50%)", "hsl(0,100%)", "hsla(0,100%,50%)", "hsl(0,120%,50%)"].iter() {
+            assert_eq!(parse_color(malformed), None, "{}", malformed);
+        }
+    }
+
+    #[test]
+    fn looks_up_named_colors() {
+        assert_eq!(named_color("red"), rgb(255, 0, 0));
+        assert_eq!(named_color("RED"), rgb(255, 0, 0));
+        assert_eq!(named_color("rebeccapurple"), rgb(0x66, 0x33, 0x99));
+        assert_eq!(named_color("aliceblue"), rgb(0xf0, 0xf8, 0xff));
+        assert_eq!(named_color("yellowgreen"), rgb(0x9a, 0xcd, 0x32));
+        assert_eq!(named_color("grey"), rgb(128, 128, 128));
+        assert_eq!(named_color("navy"), rgb(0, 0, 128));
+        assert_eq!(named_color("transparent"), Some(Color::new(0, 0, 0, 0)));
+        assert_eq!(named_color("notacolor"), None);
+        assert_eq!(parse_color("Blue"), rgb(0, 0, 255));
+    }
+
+    #[test]
+    fn converts_to_floats() {
+        assert_eq!(Color::new(255, 0, 51, 0).to_rgba_f32(), [1.0, 0.0, 0.2, 0.0]);
+    }
+}
@@ -0,0 +1,140 @@
+use parse::unescape;
+use value::split_value_list;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// One part of the generated content of a `content` declaration. `none` and `normal`
+// only come alone, the others can follow each other as in `"(" attr(title) ")"`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum ContentValue {
+    None,
+    Normal,
+    // Unescaped, without its quotes.
+    String(String),
+    // The name of the attribute whose value is inserted.
+    Attr(String),
+    Counter {name: String, style: Option<String>},
+}
+
+pub fn parse_content(value : &str) -> Option<Vec<ContentValue>> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("none") {
+        return Some(vec![ContentValue::None]);
+    }
+    if value.eq_ignore_ascii_case("normal") {
+        return Some(vec![ContentValue::Normal]);
+    }
+
+    let parts = split_parts(value)?.into_iter().map(part).collect::<Option<Vec<_>>>()?;
+    if parts.is_empty() {
+        return None;
+    }
+    return Some(parts);
+}
+
+fn part(component : &str) -> Option<ContentValue> {
+    let quote = component.chars().next()?;
+    if quote == '"' || quote == '\'' {
+        return Some(ContentValue::String(unescape(&component[1..component.len() - 1])));
+    }
+
+    let open = component.find('(')?;
+    let args = split_value_list(component[open + 1..].strip_suffix(')')?);
+    let arg = |i : usize| args.get(i).filter(|arg| is_identifier(arg)).cloned();
+    return match component[..open].to_ascii_lowercase().as_str() {
+        "attr" if args.len() == 1 => Some(ContentValue::Attr(arg(0)?)),
+        "counter" if args.len() == 1 => Some(ContentValue::Counter {name: arg(0)?, style: None}),
+        "counter" if args.len() == 2 => Some(ContentValue::Counter {name: arg(0)?, style: Some(arg(1)?)}),
+        _ => None
+    }
+}
+
+fn is_identifier(text : &str) -> bool {
+    return !text.is_empty() && text.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+}
+
+// Splits on whitespace outside strings and parentheses. Unclosed strings or functions
+// make the whole value invalid.
+fn split_parts(value : &str) -> Option<Vec<&str>> {
+    let mut parts = Vec::new();
+    let mut start = None;
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+
+    for (i, c) in value.char_indices() {
+        if start.is_none() {
+            if c.is_whitespace() {
+                continue;
+            }
+            start = Some(i);
+        }
+
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            x if Some(x) == quote => {
+                quote = None;
+                // A string ends its part, so `"a""b"` is two strings.
+                if depth == 0 {
+                    parts.push(&value[start.take()?..i + 1]);
+                }
+            },
+            _ if quote.is_some() => {},
+            '"' | '\'' => quote = Some(c),
+            '(' => depth += 1,
+            ')' => {
+                if depth == 0 {
+                    return None;
+                }
+                depth -= 1;
+                if depth == 0 {
+                    parts.push(&value[start.take()?..i + 1]);
+                }
+            },
+            _ if c.is_whitespace() && depth == 0 => parts.push(&value[start.take()?..i]),
+            _ => {}
+        }
+    }
+
+    if quote.is_some() || depth != 0 || escaped {
+        return None;
+    }
+    if let Some(start) = start {
+        parts.push(&value[start..]);
+    }
+    return Some(parts);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_strings_and_keywords() {
+        assert_eq!(parse_content("\"x\""), Some(vec![ContentValue::String("x".to_string())]));
+        assert_eq!(parse_content("none"), Some(vec![ContentValue::None]));
+        assert_eq!(parse_content("NORMAL"), Some(vec![ContentValue::Normal]));
+        assert_eq!(parse_content("'a\\'b'\"c\""), Some(vec![ContentValue::String("a'b".to_string()), ContentValue::String("c".to_string())]));
+    }
+
+    #[test]
+    fn parses_functions() {
+        assert_eq!(parse_content("attr(data-label)"), Some(vec![ContentValue::Attr("data-label".to_string())]));
+        assert_eq!(parse_content("\"(\" counter(item, upper-roman) ')'"), Some(vec![
+            ContentValue::String("(".to_string()),
+            ContentValue::Counter {name: "item".to_string(), style: Some("upper-roman".to_string())},
+            ContentValue::String(")".to_string()),
+        ]));
+    }
+
+    #[test]
+    fn rejects_malformed_content() {
+        for malformed in ["none \"x\"", "attr(a b)", "\"x", "open-quote", "attr(x))", ""].iter() {
+            assert_eq!(parse_content(malformed), None, "{}", malformed);
+        }
+    }
+}
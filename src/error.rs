@@ -0,0 +1,69 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum CssError {
+    Io(io::Error),
+    UnexpectedEof { line: usize, column: usize },
+    UnterminatedString { line: usize, column: usize },
+    UnterminatedBlock { line: usize, column: usize },
+    DepthExceeded { line: usize, column: usize },
+    UnexpectedToken { token: char, line: usize, column: usize },
+}
+
+impl fmt::Display for CssError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CssError::Io(ref why) => write!(f, "{}", why),
+            CssError::UnexpectedEof {line, column} =>
+                write!(f, "unexpected end of input at line {}, column {}", line, column),
+            CssError::UnterminatedString {line, column} =>
+                write!(f, "unterminated string at line {}, column {}", line, column),
+            CssError::UnterminatedBlock {line, column} =>
+                write!(f, "unterminated block at line {}, column {}", line, column),
+            CssError::DepthExceeded {line, column} =>
+                write!(f, "nesting too deep at line {}, column {}", line, column),
+            CssError::UnexpectedToken {token, line, column} =>
+                write!(f, "unexpected '{}' at line {}, column {}", token, line, column),
+        }
+    }
+}
+
+impl Error for CssError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        return match *self {
+            CssError::Io(ref why) => Some(why),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for CssError {
+    fn from(why: io::Error) -> CssError {
+        return CssError::Io(why);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn displays_positions() {
+        assert_eq!(CssError::DepthExceeded {line: 1, column: 2}.to_string(), "nesting too deep at line 1, column 2");
+        assert_eq!(CssError::UnexpectedToken {token: '}', line: 1, column: 1}.to_string(), "unexpected '}' at line 1, column 1");
+    }
+
+    #[test]
+    fn chains_io_errors() {
+        let error = ::parse_str("a {\n  top: 1px;").unwrap_err();
+        assert_eq!(error.to_string(), "unterminated block at line 1, column 3");
+        assert!(error.source().is_none());
+
+        let error = ::parse_file("/nonexistent.css").unwrap_err();
+        assert!(error.source().is_some());
+        let boxed: Box<dyn Error> = error.into();
+        assert!(!boxed.to_string().is_empty());
+    }
+}
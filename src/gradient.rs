@@ -0,0 +1,176 @@
+use color::{Color, parse_color};
+use value::split_value_list;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// A `linear-gradient()`. The angle is in degrees clockwise from pointing up, as with
+// `0deg`, and each stop position is a fraction of the gradient line.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Gradient {
+    pub angle: f32,
+    pub stops: Vec<(Color, f32)>,
+}
+impl Gradient {
+    // The color at `t` along the gradient line, holding the end colors past the first
+    // and last stops. A gradient without stops, which `parse_gradient` never makes, is
+    // transparent.
+    pub fn color_at(&self, t : f32) -> [f32; 4] {
+        let first = match self.stops.first() {
+            Some(first) => first,
+            None => return [0.0, 0.0, 0.0, 0.0]
+        };
+        if t <= first.1 {
+            return first.0.to_rgba_f32();
+        }
+
+        for pair in self.stops.windows(2) {
+            let ((from, start), (to, end)) = (pair[0], pair[1]);
+            if t <= end {
+                let amount = if end > start { (t - start) / (end - start) } else { 1.0 };
+                let (from, to) = (from.to_rgba_f32(), to.to_rgba_f32());
+                let mix = |i : usize| from[i] + (to[i] - from[i]) * amount;
+                return [mix(0), mix(1), mix(2), mix(3)];
+            }
+        }
+
+        return self.stops[self.stops.len() - 1].0.to_rgba_f32();
+    }
+}
+
+// `linear-gradient([<angle> | to <side or corner>,] <color> [<percentage>], ...)` with
+// at least two stops. Corners are taken as exact diagonals, whatever the box shape.
+pub fn parse_gradient(s : &str) -> Option<Gradient> {
+    let s = s.trim();
+    let open = s.find('(')?;
+    if !s[..open].trim().eq_ignore_ascii_case("linear-gradient") || !s.ends_with(')') {
+        return None;
+    }
+
+    let mut args = split_value_list(&s[open + 1..s.len() - 1]);
+    let angle = match args.first().and_then(|arg| direction(arg)) {
+        Some(angle) => {
+            args.remove(0);
+            angle
+        },
+        None => 180.0
+    };
+
+    let stops = args.iter().map(|arg| color_stop(arg)).collect::<Option<Vec<_>>>()?;
+    if stops.len() < 2 {
+        return None;
+    }
+
+    return Some(Gradient {angle, stops: spread_stops(stops)});
+}
+
+fn direction(arg : &str) -> Option<f32> {
+    let arg = arg.to_ascii_lowercase();
+    return match arg.strip_prefix("to ") {
+        Some(sides) => side_angle(sides),
+        None => parse_angle(&arg)
+    }
+}
+
+fn side_angle(sides : &str) -> Option<f32> {
+    let (mut x, mut y) = (0.0f32, 0.0f32);
+    let sides: Vec<_> = sides.split_whitespace().collect();
+    for &side in sides.iter() {
+        match side {
+            "top" if y == 0.0 => y = 1.0,
+            "bottom" if y == 0.0 => y = -1.0,
+            "right" if x == 0.0 => x = 1.0,
+            "left" if x == 0.0 => x = -1.0,
+            _ => return None
+        }
+    }
+
+    if sides.is_empty() || sides.len() > 2 {
+        return None;
+    }
+    return Some(x.atan2(y).to_degrees().rem_euclid(360.0));
+}
+
+pub(crate) fn parse_angle(arg : &str) -> Option<f32> {
+    let units = [("deg", 1.0), ("grad", 0.9), ("rad", 180.0 / std::f32::consts::PI), ("turn", 360.0)];
+    for &(unit, degrees) in units.iter() {
+        if let Some(number) = arg.strip_suffix(unit) {
+            return number.parse::<f32>().ok().map(|number| number * degrees);
+        }
+    }
+    return if arg == "0" { Some(0.0) } else { None };
+}
+
+fn color_stop(arg : &str) -> Option<(Color, Option<f32>)> {
+    if let Some(color) = parse_color(arg) {
+        return Some((color, None));
+    }
+
+    let split = arg.rfind(char::is_whitespace)?;
+    let position = arg[split..].trim().strip_suffix('%')?.parse::<f32>().ok()?;
+    return Some((parse_color(&arg[..split])?, Some(position / 100.0)));
+}
+
+// Stops without a position are spaced evenly between their neighbours, with the ends
+// at 0 and 1, and no stop may come before the one preceding it.
+fn spread_stops(stops : Vec<(Color, Option<f32>)>) -> Vec<(Color, f32)> {
+    let last = stops.len() - 1;
+    let mut positions: Vec<_> = stops.iter().map(|&(_, position)| position).collect();
+    positions[0] = positions[0].or(Some(0.0));
+    positions[last] = positions[last].or(Some(1.0));
+
+    let mut previous = 0;
+    for i in 1..=last {
+        if let Some(end) = positions[i] {
+            let start = positions[previous].unwrap();
+            for (step, position) in positions[previous + 1..i].iter_mut().enumerate() {
+                *position = Some(start + (end - start) * (step + 1) as f32 / (i - previous) as f32);
+            }
+            previous = i;
+        }
+    }
+
+    let mut floor = f32::MIN;
+    return stops.iter().zip(positions).map(|(&(color, _), position)| {
+        floor = floor.max(position.unwrap());
+        (color, floor)
+    }).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color(name : &str) -> Color {
+        return parse_color(name).unwrap();
+    }
+
+    #[test]
+    fn parses_angles_and_stops() {
+        let gradient = parse_gradient("linear-gradient(to right, red, blue)").unwrap();
+        assert_eq!(gradient, Gradient {angle: 90.0, stops: vec![(color("red"), 0.0), (color("blue"), 1.0)]});
+
+        let gradient = parse_gradient("linear-gradient(45deg, rgb(1, 2, 3) 10%, lime, blue 90%, red)").unwrap();
+        assert_eq!(gradient.angle, 45.0);
+        let positions: Vec<_> = gradient.stops.iter().map(|&(_, position)| (position * 1000.0).round()).collect();
+        assert_eq!(positions, vec![100.0, 500.0, 900.0, 1000.0]);
+
+        assert_eq!(parse_gradient("linear-gradient(red, blue)").unwrap().angle, 180.0);
+        assert_eq!(parse_gradient("linear-gradient(to top left, red, blue)").unwrap().angle, 315.0);
+        assert_eq!(parse_gradient("linear-gradient(0.5turn, red, blue)").unwrap().angle, 180.0);
+    }
+
+    #[test]
+    fn rejects_malformed_gradients() {
+        for malformed in ["linear-gradient(to right, red)", "linear-gradient(to left right, red, blue)", "linear-gradient()"].iter() {
+            assert!(parse_gradient(malformed).is_none(), "{}", malformed);
+        }
+    }
+
+    #[test]
+    fn gradients_without_stops_are_transparent() {
+        let gradient = Gradient {angle: 180.0, stops: vec![]};
+        assert_eq!(gradient.color_at(0.5), [0.0, 0.0, 0.0, 0.0]);
+    }
+}
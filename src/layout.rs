@@ -0,0 +1,459 @@
+use calc::parse_calc;
+use cascade::{is_hover_only, with_hover};
+use color::{Color, parse_color};
+use gradient::Gradient;
+use length::{Length, parse_length};
+use parse::{CssRoot, CssRuleSet};
+use shorthand::expand_rule_set;
+use transform::Transform;
+use value::{CssValue, parse_value};
+
+const SIDES: [&str; 4] = ["top", "right", "bottom", "left"];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+impl Rect {
+    pub fn new(x : f32, y : f32, width : f32, height : f32) -> Rect {
+        return Rect {x, y, width, height};
+    }
+
+    // Includes the top and left edges but not the bottom and right ones, so
+    // boxes that share an edge never both contain a point on it.
+    pub fn contains(&self, x : f32, y : f32) -> bool {
+        return x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height;
+    }
+
+    // Grows the rect by the given top, right, bottom and left amounts.
+    fn expand(&self, (top, right, bottom, left) : (f32, f32, f32, f32)) -> Rect {
+        return Rect::new(self.x - left, self.y - top, self.width + left + right, self.height + top + bottom);
+    }
+}
+
+// The `display` of a rule set. `none` leaves the box and everything nested in it out,
+// and `flex` lays the nested rule sets out in a row, see `layout_flex_row`. Anything
+// else is laid out as a positioned block.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Display {
+    Block,
+    Inline,
+    InlineBlock,
+    Flex,
+    None,
+}
+
+// The nested content, padding, border and margin boxes of one absolutely positioned rule set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutBox {
+    pub selectors: Vec<String>,
+    pub background: Option<Color>,
+    // Painted instead of `background` when set.
+    pub background_gradient: Option<Gradient>,
+    pub border_radius: f32,
+    pub opacity: f32,
+    // In top, right, bottom, left order, like the widths the border box adds.
+    pub border_colors: [Color; 4],
+    pub content: Rect,
+    pub padding: Rect,
+    pub border: Rect,
+    pub margin: Rect,
+    pub z_index: i32,
+    pub display: Display,
+    // Applied when painting, without moving the boxes around it.
+    pub transforms: Vec<Transform>,
+}
+
+// Lays out every rule set with a sized `width` and `height`, placing its margin edge at
+// `left`/`top`. `width` and `height` size the content box, as with `box-sizing: content-box`.
+// Percentages resolve against the containing block: the viewport for top-level rule sets
+// and the content box of the parent for nested ones, which are also offset from it.
+// Rule sets with `display: none` are left out, with everything nested in them.
+// The rule sets nested in a sized `display: flex` one are its flex items instead.
+// The boxes come back in paint order, see `sort_by_z_index`.
+pub fn layout(root : &CssRoot, viewport : (f32, f32)) -> Vec<LayoutBox> {
+    return layout_hovered(root, viewport, None);
+}
+
+// As `layout`, with the `:hover` rule sets applied to the rule set whose selectors are
+// `hovered`, like those of the box under the cursor.
+pub fn layout_hovered(root : &CssRoot, viewport : (f32, f32), hovered : Option<&[String]>) -> Vec<LayoutBox> {
+    let context = LayoutContext {root, viewport, hovered};
+    let mut boxes = Vec::new();
+    let container = Rect::new(0.0, 0.0, viewport.0, viewport.1);
+    for rule_set in root.rule_sets.iter() {
+        layout_nested(rule_set, container, context, &mut boxes);
+    }
+
+    sort_by_z_index(&mut boxes);
+    return boxes;
+}
+
+// What stays the same while laying out one stylesheet.
+#[derive(Clone, Copy)]
+struct LayoutContext<'a> {
+    root: &'a CssRoot,
+    viewport: (f32, f32),
+    hovered: Option<&'a [String]>,
+}
+
+// The rule set as it is laid out: with its `:hover` rules when it is the hovered one,
+// and its shorthands expanded.
+fn styled(rule_set : &CssRuleSet, context : LayoutContext) -> CssRuleSet {
+    let mut styled = if context.hovered == Some(&rule_set.selectors[..]) {
+        with_hover(context.root, rule_set)
+    } else {
+        rule_set.clone()
+    };
+    expand_rule_set(&mut styled);
+    return styled;
+}
+
+fn layout_nested(rule_set : &CssRuleSet, container : Rect, context : LayoutContext, boxes : &mut Vec<LayoutBox>) {
+    let styled = styled(rule_set, context);
+    if display(&styled) == Display::None {
+        return;
+    }
+
+    let layout_box = if is_hover_only(rule_set) { None } else { layout_box(&styled, container, context.viewport) };
+    match layout_box {
+        Some(layout_box) => {
+            let content = layout_box.content;
+            boxes.push(layout_box);
+            layout_children(&styled, content, context, boxes);
+        },
+        None => {
+            for child in children(rule_set) {
+                layout_nested(&child, container, context, boxes);
+            }
+        }
+    }
+}
+
+// Lays out what is nested in the box of `rule_set`, whose content box is `content`.
+fn layout_children(rule_set : &CssRuleSet, content : Rect, context : LayoutContext, boxes : &mut Vec<LayoutBox>) {
+    if display(rule_set) == Display::Flex {
+        layout_flex_row(rule_set, content, context, boxes);
+        return;
+    }
+    for child in children(rule_set) {
+        layout_nested(&child, content, context, boxes);
+    }
+}
+
+// The nested rule sets with their selectors written out in full.
+fn children(rule_set : &CssRuleSet) -> Vec<CssRuleSet> {
+    return rule_set.children.iter()
+        .map(|child| {
+            let mut child = child.clone();
+            child.selectors = rule_set.nested_selectors(&child);
+            child
+        })
+        .collect();
+}
+
+// Places the flex items of `rule_set` left to right along its content box, `gap` apart.
+// Each starts at its `flex-basis` or `width`, or at nothing, and the free space left
+// is shared out by `flex-grow`; items don't shrink when there is too little. Items
+// without a `height` are stretched to the height of the container. `left` and `top`
+// don't move flex items.
+fn layout_flex_row(rule_set : &CssRuleSet, content : Rect, context : LayoutContext, boxes : &mut Vec<LayoutBox>) {
+    let viewport = context.viewport;
+    let items: Vec<_> = children(rule_set).iter()
+        .filter(|item| !is_hover_only(item))
+        .map(|item| styled(item, context))
+        .filter(|item| display(item) != Display::None)
+        .collect();
+
+    let gap = column_gap(rule_set, viewport, content.width);
+    let insets: Vec<_> = items.iter().map(|item| box_insets(item, viewport, content.width)).collect();
+    let bases: Vec<_> = items.iter()
+        .map(|item| px(item, "flex-basis", viewport, content.width).or_else(|| px(item, "width", viewport, content.width)))
+        .map(|basis| basis.unwrap_or(0.0).max(0.0))
+        .collect();
+    let grows: Vec<_> = items.iter().map(flex_grow).collect();
+
+    let gaps = gap * items.len().saturating_sub(1) as f32;
+    let used = bases.iter().sum::<f32>() + insets.iter().map(|&(_, x, _, y)| x + y).sum::<f32>() + gaps;
+    let free = (content.width - used).max(0.0);
+    let total_grow = grows.iter().sum::<f32>();
+
+    let mut x = content.x;
+    for (i, item) in items.iter().enumerate() {
+        let share = if total_grow > 0.0 { free * grows[i] / total_grow } else { 0.0 };
+        let width = bases[i] + share;
+        let stretched = content.height - insets[i].0 - insets[i].2;
+        let height = px(item, "height", viewport, content.height).unwrap_or(stretched.max(0.0));
+
+        let layout_box = sized_box(item, content, viewport, (x, content.y), (width, height));
+        x = layout_box.margin.x + layout_box.margin.width + gap;
+        let item_content = layout_box.content;
+        boxes.push(layout_box);
+        layout_children(item, item_content, context, boxes);
+    }
+}
+
+// The space between the items of a flex row: `column-gap`, or the second or only value
+// of `gap`.
+fn column_gap(rule_set : &CssRuleSet, viewport : (f32, f32), basis : f32) -> f32 {
+    let gap = rule_set.get("column-gap")
+        .or_else(|| rule_set.get("gap").and_then(|gap| gap.split_whitespace().last()));
+    return gap.and_then(|gap| length_px(gap, viewport, Some(basis))).unwrap_or(0.0).max(0.0);
+}
+
+fn flex_grow(rule_set : &CssRuleSet) -> f32 {
+    return match rule_set.get("flex-grow").map(|value| parse_value("flex-grow", value)) {
+        Some(CssValue::Number(grow)) if grow > 0.0 => grow,
+        _ => 0.0
+    }
+}
+
+// Orders boxes from the bottom of the stack up. The sort is stable, so boxes with the
+// same `z-index` keep their source order and later ones are painted over earlier ones.
+pub fn sort_by_z_index(boxes : &mut [LayoutBox]) {
+    boxes.sort_by_key(|layout_box| layout_box.z_index);
+}
+
+// `auto` and anything that isn't a number stack at zero.
+pub(crate) fn z_index(rule_set : &CssRuleSet) -> i32 {
+    return match rule_set.get("z-index").map(|value| parse_value("z-index", value)) {
+        Some(CssValue::Number(z_index)) => z_index as i32,
+        _ => 0
+    }
+}
+
+// `block` when missing or not one of the values `Display` knows.
+pub(crate) fn display(rule_set : &CssRuleSet) -> Display {
+    return match rule_set.get("display").map(|value| value.trim().to_ascii_lowercase()).as_deref() {
+        Some("inline") => Display::Inline,
+        Some("inline-block") => Display::InlineBlock,
+        Some("flex") => Display::Flex,
+        Some("none") => Display::None,
+        _ => Display::Block
+    }
+}
+
+// The functions of `transform`, or none when it is missing or can't be parsed.
+pub(crate) fn transforms(rule_set : &CssRuleSet) -> Vec<Transform> {
+    return match rule_set.get("transform").map(|value| parse_value("transform", value)) {
+        Some(CssValue::Transform(transforms)) => transforms,
+        _ => Vec::new()
+    }
+}
+
+fn layout_box(rule_set : &CssRuleSet, container : Rect, viewport : (f32, f32)) -> Option<LayoutBox> {
+    let width = px(rule_set, "width", viewport, container.width)?;
+    let height = px(rule_set, "height", viewport, container.height)?;
+    let left = container.x + px(rule_set, "left", viewport, container.width).unwrap_or(0.0);
+    let top = container.y + px(rule_set, "top", viewport, container.height).unwrap_or(0.0);
+    return Some(sized_box(rule_set, container, viewport, (left, top), (width, height)));
+}
+
+// The box with its margin edge at `origin` and a content box of `size`.
+fn sized_box(rule_set : &CssRuleSet, container : Rect, viewport : (f32, f32), (left, top) : (f32, f32),
+    (width, height) : (f32, f32)) -> LayoutBox {
+    // Percentage margins and padding resolve against the container width on every side.
+    let margin = sides(rule_set, viewport, container.width, |side| format!("margin-{}", side));
+    let border = border_widths(rule_set, viewport);
+    let padding = sides(rule_set, viewport, container.width, |side| format!("padding-{}", side));
+
+    let (inset_x, inset_y) = (margin.3 + border.3 + padding.3, margin.0 + border.0 + padding.0);
+    let content = Rect::new(left + inset_x, top + inset_y, width, height);
+    let padding_box = content.expand(padding);
+    let border_box = padding_box.expand(border);
+
+    return LayoutBox {
+        selectors: rule_set.selectors.clone(),
+        background: rule_set.get("background-color").and_then(parse_color),
+        background_gradient: background_gradient(rule_set),
+        border_radius: px(rule_set, "border-radius", viewport, width).unwrap_or(0.0).max(0.0),
+        opacity: opacity(rule_set),
+        border_colors: border_colors(rule_set),
+        content,
+        padding: padding_box,
+        border: border_box,
+        margin: border_box.expand(margin),
+        z_index: z_index(rule_set),
+        display: display(rule_set),
+        transforms: transforms(rule_set),
+    };
+}
+
+fn background_gradient(rule_set : &CssRuleSet) -> Option<Gradient> {
+    let key = if rule_set.get("background-image").is_some() { "background-image" } else { "background" };
+    return match parse_value(key, rule_set.get(key)?) {
+        CssValue::Gradient(gradient) => Some(gradient),
+        _ => None
+    }
+}
+
+// `opacity` as a number or percentage, defaulting to fully opaque.
+fn opacity(rule_set : &CssRuleSet) -> f32 {
+    return match rule_set.get("opacity").map(|value| parse_value("opacity", value)) {
+        Some(CssValue::Number(opacity)) => opacity,
+        Some(CssValue::Percentage(percent)) => percent / 100.0,
+        _ => 1.0
+    }
+}
+
+// How far the content box of a box sits inside its margin box on each side, in top,
+// right, bottom, left order.
+fn box_insets(rule_set : &CssRuleSet, viewport : (f32, f32), basis : f32) -> (f32, f32, f32, f32) {
+    let margin = sides(rule_set, viewport, basis, |side| format!("margin-{}", side));
+    let border = border_widths(rule_set, viewport);
+    let padding = sides(rule_set, viewport, basis, |side| format!("padding-{}", side));
+    return (
+        margin.0 + border.0 + padding.0,
+        margin.1 + border.1 + padding.1,
+        margin.2 + border.2 + padding.2,
+        margin.3 + border.3 + padding.3,
+    );
+}
+
+fn sides<F: Fn(&str) -> String>(rule_set : &CssRuleSet, viewport : (f32, f32), basis : f32, key : F) -> (f32, f32, f32, f32) {
+    let side = |i : usize| px(rule_set, &key(SIDES[i]), viewport, basis).unwrap_or(0.0);
+    return (side(0), side(1), side(2), side(3));
+}
+
+// Borders only take up space on the sides with a visible style.
+fn border_widths(rule_set : &CssRuleSet, viewport : (f32, f32)) -> (f32, f32, f32, f32) {
+    let style = rule_set.get("border-style").unwrap_or("none");
+    let width = rule_set.get("border-width").and_then(|value| border_width(value, viewport)).unwrap_or(0.0);
+    let side = |i : usize| {
+        let side_style = rule_set.get(&format!("border-{}-style", SIDES[i])).unwrap_or(style);
+        if side_style == "none" || side_style == "hidden" {
+            return 0.0;
+        }
+        return rule_set.get(&format!("border-{}-width", SIDES[i]))
+            .and_then(|value| border_width(value, viewport))
+            .unwrap_or(width);
+    };
+    return (side(0), side(1), side(2), side(3));
+}
+
+// `currentcolor`, the default, takes the `color` of the rule set, or black without one.
+fn border_colors(rule_set : &CssRuleSet) -> [Color; 4] {
+    let current = rule_set.get("color").and_then(parse_color).unwrap_or(Color::new(0, 0, 0, 255));
+    let color = rule_set.get("border-color").and_then(parse_color).unwrap_or(current);
+    let side = |i : usize| {
+        rule_set.get(&format!("border-{}-color", SIDES[i]))
+            .map(|value| parse_color(value).unwrap_or(current))
+            .unwrap_or(color)
+    };
+    return [side(0), side(1), side(2), side(3)];
+}
+
+fn border_width(value : &str, viewport : (f32, f32)) -> Option<f32> {
+    return match value {
+        "thin" => Some(1.0),
+        "medium" => Some(3.0),
+        "thick" => Some(5.0),
+        _ => length_px(value, viewport, None)
+    }
+}
+
+// `basis` is the length percentages are taken of.
+fn px(rule_set : &CssRuleSet, key : &str, viewport : (f32, f32), basis : f32) -> Option<f32> {
+    return length_px(rule_set.get(key)?, viewport, Some(basis));
+}
+
+// A `calc()` is evaluated with its lengths resolved like plain ones.
+fn length_px(value : &str, viewport : (f32, f32), basis : Option<f32>) -> Option<f32> {
+    if let Some(calc) = parse_calc(value).filter(|calc| calc.is_length()) {
+        return calc.eval(&|length| resolve(length, viewport, basis));
+    }
+    return resolve(parse_length(value)?, viewport, basis);
+}
+
+fn resolve(length : Length, (width, height) : (f32, f32), basis : Option<f32>) -> Option<f32> {
+    return match length {
+        Length::Px(value) => Some(value),
+        Length::Vw(value) => Some(value * width / 100.0),
+        Length::Vh(value) => Some(value * height / 100.0),
+        Length::Percent(value) => basis.map(|basis| value * basis / 100.0),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn borders_take_space_on_styled_sides() {
+        let root = ::parse_str("a { width: 10px; height: 10px; border-width: 2px; border-style: solid none }").unwrap();
+        let boxes = layout(&root, (100.0, 100.0));
+        assert_eq!(boxes[0].content, Rect::new(0.0, 2.0, 10.0, 10.0));
+        assert_eq!(boxes[0].border, Rect::new(0.0, 0.0, 10.0, 14.0));
+    }
+
+    #[test]
+    fn resolves_calc_lengths() {
+        let root = ::parse_str("a { width: calc(100% - 20px); height: calc(50vh + 1px); }").unwrap();
+        let boxes = layout(&root, (800.0, 600.0));
+        assert_eq!((boxes[0].content.width, boxes[0].content.height), (780.0, 301.0));
+    }
+
+    #[test]
+    fn inset_places_the_box() {
+        let root = ::parse_str("a { inset: 10px auto auto 20%; width: 5px; height: 5px }").unwrap();
+        let boxes = layout(&root, (100.0, 100.0));
+        assert_eq!(boxes[0].margin, Rect::new(20.0, 10.0, 5.0, 5.0));
+    }
+
+    #[test]
+    fn nests_the_box_model() {
+        let root = ::parse_str("a { left: 5px; top: 5px; width: 10px; height: 20px; margin: 1px; padding: 2px 3px; \
+            border: 4px solid red } b { color: red }").unwrap();
+        let boxes = layout(&root, (100.0, 100.0));
+        assert_eq!(boxes.len(), 1);
+        assert_eq!(boxes[0].margin, Rect::new(5.0, 5.0, 26.0, 34.0));
+        assert_eq!(boxes[0].border, Rect::new(6.0, 6.0, 24.0, 32.0));
+        assert_eq!(boxes[0].padding, Rect::new(10.0, 10.0, 16.0, 24.0));
+        assert_eq!(boxes[0].content, Rect::new(13.0, 12.0, 10.0, 20.0));
+        assert_eq!(boxes[0].border_colors, [Color::new(255, 0, 0, 255); 4]);
+    }
+
+    #[test]
+    fn percentages_resolve_against_the_parent() {
+        let root = ::parse_str("a { width: 50%; height: 50%; padding: 10%; \
+            & b { left: 10%; width: 50%; height: 25%; margin-top: 10% } }").unwrap();
+        let boxes = layout(&root, (400.0, 200.0));
+        assert_eq!(boxes[0].content, Rect::new(40.0, 40.0, 200.0, 100.0));
+        assert_eq!(boxes[1].content, Rect::new(60.0, 60.0, 100.0, 25.0));
+    }
+
+    #[test]
+    fn display_none_leaves_out_nested_boxes() {
+        let root = ::parse_str("a { display: none; width: 10px; height: 10px; & b { width: 5px; height: 5px } } \
+            c { width: 1px; height: 1px; display: inline-block }").unwrap();
+        let boxes = layout(&root, (100.0, 100.0));
+        assert_eq!(boxes.len(), 1);
+        assert_eq!(boxes[0].selectors, vec!["c".to_string()]);
+        assert_eq!(boxes[0].display, Display::InlineBlock);
+    }
+
+    #[test]
+    fn flex_rows_share_the_free_space() {
+        let root = ::parse_str("row { display: flex; width: 300px; height: 50px; gap: 10px; \
+            & .a { width: 50px; flex-grow: 1; left: 99px } \
+            & .b { flex-basis: 30px; flex-grow: 2; height: 20px } \
+            & .c { display: none; width: 500px } }").unwrap();
+        let boxes = layout(&root, (400.0, 400.0));
+        assert_eq!(boxes.len(), 3);
+        assert_eq!(boxes[1].content, Rect::new(0.0, 0.0, 120.0, 50.0));
+        assert_eq!(boxes[2].content, Rect::new(130.0, 0.0, 170.0, 20.0));
+    }
+
+    #[test]
+    fn equal_flex_grow_splits_the_row_evenly() {
+        let root = ::parse_str("row { display: flex; width: 300px; height: 50px; \
+            & .a { flex-grow: 1 } & .b { flex-grow: 1 } }").unwrap();
+        let boxes = layout(&root, (400.0, 400.0));
+        assert_eq!(boxes[1].content, Rect::new(0.0, 0.0, 150.0, 50.0));
+        assert_eq!(boxes[2].content, Rect::new(150.0, 0.0, 150.0, 50.0));
+    }
+}
@@ -0,0 +1,62 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Length {
+    Px(f32),
+    Em(f32),
+    Rem(f32),
+    Percent(f32),
+    Vh(f32),
+    Vw(f32),
+    Auto,
+}
+
+pub fn parse_length(s : &str) -> Option<Length> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("auto") {
+        return Some(Length::Auto);
+    }
+
+    let split = s.find(|c : char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let value = number.parse::<f32>().ok()?;
+
+    return match unit.to_ascii_lowercase().as_str() {
+        "px" => Some(Length::Px(value)),
+        "em" => Some(Length::Em(value)),
+        "rem" => Some(Length::Rem(value)),
+        "%" => Some(Length::Percent(value)),
+        "vh" => Some(Length::Vh(value)),
+        "vw" => Some(Length::Vw(value)),
+        "" if value == 0.0 => Some(Length::Px(0.0)),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_each_unit() {
+        assert_eq!(parse_length("12px"), Some(Length::Px(12.0)));
+        assert_eq!(parse_length("1.5em"), Some(Length::Em(1.5)));
+        assert_eq!(parse_length("2rem"), Some(Length::Rem(2.0)));
+        assert_eq!(parse_length("100%"), Some(Length::Percent(100.0)));
+        assert_eq!(parse_length("50vh"), Some(Length::Vh(50.0)));
+        assert_eq!(parse_length("-10vw"), Some(Length::Vw(-10.0)));
+        assert_eq!(parse_length("auto"), Some(Length::Auto));
+    }
+
+    #[test]
+    fn only_zero_goes_without_a_unit() {
+        assert_eq!(parse_length("0"), Some(Length::Px(0.0)));
+        for malformed in ["10", "px", "10pt", "", "1..2px"].iter() {
+            assert_eq!(parse_length(malformed), None, "{}", malformed);
+        }
+    }
+}
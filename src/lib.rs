@@ -0,0 +1,107 @@
+#![allow(clippy::needless_return)]
+
+extern crate cgmath;
+extern crate image;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_json;
+
+mod calc;
+mod capture;
+mod cascade;
+mod color;
+mod content;
+mod error;
+mod gradient;
+mod layout;
+mod length;
+mod media;
+mod offscreen;
+mod paint;
+mod parse;
+mod reload;
+mod selector;
+mod serialize;
+mod shadow;
+mod shorthand;
+mod text;
+mod token;
+mod transform;
+mod validate;
+mod value;
+mod vars;
+mod window;
+
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+
+pub use calc::{CalcExpr, parse_calc};
+pub use capture::{capture_frame, save_screenshot};
+pub use cascade::{compute_for_classes, computed, computed_with_hover};
+pub use color::{Color, named_color, parse_color};
+pub use content::{ContentValue, parse_content};
+pub use error::CssError;
+pub use gradient::{Gradient, parse_gradient};
+pub use layout::{Display, LayoutBox, Rect, layout, layout_hovered, sort_by_z_index};
+pub use length::{Length, parse_length};
+pub use media::{MediaBound, MediaFeature, MediaQuery, parse_media_queries};
+pub use offscreen::render_offscreen;
+pub use paint::{CLEAR_COLOR, Vertex, apply_opacity, border_vertices, clear_color, gradient_vertices, ortho_for, paint,
+    paint_boxes, paint_hovered, rect_vertices, rounded_rect_vertices};
+pub use parse::{CssParser, CssRoot, CssFontFace, CssKeyframes, CssMediaQuery, CssRuleSet, CssRule, unescape,
+    unprefixed};
+pub use reload::{LiveStylesheet, StyleCache};
+pub use selector::{Combinator, Compound, PseudoClass, Selector, Specificity, parse_selector, specificity};
+pub use serialize::{minify, to_string_with_indent};
+pub use shadow::{BoxShadow, parse_box_shadow};
+pub use shorthand::expand_shorthands;
+pub use text::{FontAtlas, GlyphCache, TexturedVertex, text_vertices};
+pub use token::{CssToken, tokenize};
+pub use transform::{Transform, parse_transform, transform_matrix};
+pub use validate::{ValidationWarning, validate};
+pub use value::{CssValue, parse_value, split_value_list};
+pub use vars::resolve_vars;
+pub use window::{FrameTimer, RenderState, SPIN_DEGREES_PER_SECOND, WindowOptions, spin_matrix};
+
+pub fn parse_str(css : &str) -> Result<CssRoot, CssError> {
+    return CssParser::new().parse_bytes(css);
+}
+
+pub fn parse_file<P: AsRef<Path>>(path : P) -> Result<CssRoot, CssError> {
+    let mut file = File::open(path)?;
+    let mut css = String::new();
+    file.read_to_string(&mut css)?;
+
+    return CssParser::new().parse(css);
+}
+
+// Parses named stylesheets, like a base and a theme, into one root as if they were
+// concatenated in order, with each rule set recording the name and line it came from.
+// The `@charset` of the first source that has one is kept.
+pub fn parse_many(sources : &[(String, &str)]) -> Result<CssRoot, CssError> {
+    let mut root = CssRoot::new();
+    for &(ref name, css) in sources.iter() {
+        root.merge(CssParser::new().with_source(name).parse_bytes(css)?);
+    }
+    return Ok(root);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_many_records_sources() {
+        let base = "a { color: red }\n\n.b,\n.c {\n  color: blue;\n  & d { x: y }\n}\n";
+        let theme = "@media screen {\n  a { color: green }\n}\na { color: black }";
+        let root = parse_many(&[("base.css".to_string(), base), ("theme.css".to_string(), theme)]).unwrap();
+
+        let sources: Vec<_> = root.rule_sets.iter().map(|rule_set| rule_set.source.clone().unwrap()).collect();
+        assert_eq!(sources, vec![("base.css".to_string(), 1), ("base.css".to_string(), 3), ("theme.css".to_string(), 4)]);
+        assert_eq!(root.rule_sets[1].children[0].source, Some(("base.css".to_string(), 6)));
+        assert_eq!(root.media_queries[0].rule_sets[0].source, Some(("theme.css".to_string(), 2)));
+        assert_eq!(parse_str(base).unwrap().rule_sets[0].source, None);
+    }
+}
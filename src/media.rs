@@ -0,0 +1,294 @@
+use length::{Length, parse_length};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// `em` and `rem` in media features are relative to the initial font size.
+const INITIAL_FONT_SIZE: f32 = 16.0;
+
+// One query of a comma separated `@media` list, like `not screen and (min-width: 600px)`.
+// It holds when the media type and every feature do, or when they don't if `negated`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MediaQuery {
+    pub negated: bool,
+    // Lowercase, and None when the query starts with a feature.
+    pub media_type: Option<String>,
+    pub conditions: Vec<MediaFeature>,
+}
+impl MediaQuery {
+    // Evaluates the query for a screen of the given size, as the window would. Width and
+    // height ranges and `orientation` are known; any other feature never matches.
+    pub fn matches(&self, viewport : (f32, f32)) -> bool {
+        let is_screen = matches!(self.media_type.as_deref(), None | Some("all") | Some("screen"));
+        let holds = is_screen && self.conditions.iter().all(|feature| feature.matches(viewport));
+        return holds != self.negated;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MediaBound {
+    pub length: Length,
+    pub inclusive: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum MediaFeature {
+    // `(min-width: 600px)` is a range over `width` with only a lower bound, and so is
+    // `(width >= 600px)`. `(400px < width < 900px)` has exclusive bounds on both sides.
+    Range {name: String, min: Option<MediaBound>, max: Option<MediaBound>},
+    // `(orientation: landscape)`, or `(color)` without a value.
+    Plain {name: String, value: Option<String>},
+}
+impl MediaFeature {
+    pub fn matches(&self, (width, height) : (f32, f32)) -> bool {
+        return match *self {
+            MediaFeature::Range {ref name, min, max} => {
+                let size = match name.as_str() {
+                    "width" => width,
+                    "height" => height,
+                    _ => return false
+                };
+                let px = |bound : MediaBound| -> Option<(f32, bool)> {
+                    let px = match bound.length {
+                        Length::Px(value) => value,
+                        Length::Em(value) | Length::Rem(value) => value * INITIAL_FONT_SIZE,
+                        Length::Vw(value) => value * width / 100.0,
+                        Length::Vh(value) => value * height / 100.0,
+                        Length::Percent(_) | Length::Auto => return None
+                    };
+                    return Some((px, bound.inclusive));
+                };
+                let above = match min.map(px) {
+                    Some(Some((min, inclusive))) => size > min || (inclusive && size == min),
+                    Some(None) => false,
+                    None => true
+                };
+                let below = match max.map(px) {
+                    Some(Some((max, inclusive))) => size < max || (inclusive && size == max),
+                    Some(None) => false,
+                    None => true
+                };
+                above && below
+            },
+            MediaFeature::Plain {ref name, ref value} => match (name.as_str(), value.as_deref()) {
+                ("orientation", Some("landscape")) => width > height,
+                ("orientation", Some("portrait")) => height >= width,
+                _ => false
+            }
+        }
+    }
+}
+
+// The queries of an `@media` condition. Malformed ones are left out, as they never
+// match, so `screen and, print` keeps only `print`.
+pub fn parse_media_queries(condition : &str) -> Vec<MediaQuery> {
+    return condition.split(',').filter_map(media_query).collect();
+}
+
+fn media_query(query : &str) -> Option<MediaQuery> {
+    let mut media_query = MediaQuery {negated: false, media_type: None, conditions: Vec::new()};
+    let mut rest = query.trim();
+    let mut started = false;
+    // Whether the last thing read was a media type or feature, which `and` must follow.
+    let mut expects_and = false;
+
+    while !rest.is_empty() {
+        if rest.starts_with('(') {
+            if expects_and {
+                return None;
+            }
+            let close = rest.find(')')?;
+            media_query.conditions.push(media_feature(&rest[1..close])?);
+            rest = rest[close + 1..].trim_start();
+            started = true;
+            expects_and = true;
+            continue;
+        }
+
+        let end = rest.find(|c : char| c.is_whitespace() || c == '(').unwrap_or(rest.len());
+        let word = rest[..end].to_ascii_lowercase();
+        rest = rest[end..].trim_start();
+        match word.as_str() {
+            "and" if expects_and => expects_and = false,
+            "not" | "only" if !started => media_query.negated = word == "not",
+            _ if media_query.media_type.is_none() && media_query.conditions.is_empty() && !expects_and
+                && is_identifier(&word) => {
+                media_query.media_type = Some(word);
+                expects_and = true;
+            },
+            _ => return None
+        }
+        started = true;
+    }
+
+    // Nothing at all, or a trailing `and` or `not`.
+    if !expects_and {
+        return None;
+    }
+    return Some(media_query);
+}
+
+fn media_feature(feature : &str) -> Option<MediaFeature> {
+    if let Some(colon) = feature.find(':') {
+        let name = feature[..colon].trim().to_ascii_lowercase();
+        let value = feature[colon + 1..].trim();
+        if !is_identifier(&name) || value.is_empty() {
+            return None;
+        }
+
+        let length = parse_length(value).filter(|&length| length != Length::Auto);
+        let bound = length.map(|length| MediaBound {length, inclusive: true});
+        if let (Some(bound), Some(range)) = (bound, name.strip_prefix("min-")) {
+            return Some(MediaFeature::Range {name: range.to_string(), min: Some(bound), max: None});
+        }
+        if let (Some(bound), Some(range)) = (bound, name.strip_prefix("max-")) {
+            return Some(MediaFeature::Range {name: range.to_string(), min: None, max: Some(bound)});
+        }
+        if bound.is_some() && (name == "width" || name == "height") {
+            return Some(MediaFeature::Range {name, min: bound, max: bound});
+        }
+        return Some(MediaFeature::Plain {name, value: Some(value.to_ascii_lowercase())});
+    }
+
+    if feature.contains(['<', '>', '=']) {
+        return range(feature);
+    }
+
+    let name = feature.trim().to_ascii_lowercase();
+    if !is_identifier(&name) {
+        return None;
+    }
+    return Some(MediaFeature::Plain {name, value: None});
+}
+
+// The range syntax, as in `width >= 600px`, `600px <= width` or `400px < width <= 900px`.
+fn range(feature : &str) -> Option<MediaFeature> {
+    let mut operands = Vec::new();
+    let mut operators = Vec::new();
+    let mut rest = feature;
+    while let Some(start) = rest.find(['<', '>', '=']) {
+        let len = if rest[start + 1..].starts_with('=') && !rest[start..].starts_with('=') { 2 } else { 1 };
+        operands.push(rest[..start].trim());
+        operators.push(&rest[start..start + len]);
+        rest = &rest[start + len..];
+    }
+    operands.push(rest.trim());
+
+    let length = |operand : &str| {
+        parse_length(operand).filter(|&length| length != Length::Auto)
+    };
+    // The bound a `length operator name` comparison puts on the name, as a (min, max) pair.
+    let bounds = |length : Length, operator : &str| -> Option<(Option<MediaBound>, Option<MediaBound>)> {
+        let bound = |inclusive : bool| Some(MediaBound {length, inclusive});
+        return match operator {
+            "<" => Some((bound(false), None)),
+            "<=" => Some((bound(true), None)),
+            ">" => Some((None, bound(false))),
+            ">=" => Some((None, bound(true))),
+            "=" => Some((bound(true), bound(true))),
+            _ => None
+        }
+    };
+    let name = |operand : &str| Some(operand.to_ascii_lowercase()).filter(|name| is_identifier(name));
+
+    let (name, min, max) = match (operands.len(), length(operands[0])) {
+        // `name op length` reads as `length flipped-op name`.
+        (2, None) => {
+            let (min, max) = bounds(length(operands[1])?, flipped(operators[0]))?;
+            (name(operands[0])?, min, max)
+        },
+        (2, Some(length)) => {
+            let (min, max) = bounds(length, operators[0])?;
+            (name(operands[1])?, min, max)
+        },
+        // Both comparisons have to point the same way, with the name in the middle.
+        (3, Some(first)) => {
+            let (first_min, first_max) = bounds(first, operators[0])?;
+            let (last_min, last_max) = bounds(length(operands[2])?, flipped(operators[1]))?;
+            let same_way = (first_max.is_none() && last_min.is_none()) || (first_min.is_none() && last_max.is_none());
+            if operators.contains(&"=") || !same_way {
+                return None;
+            }
+            (name(operands[1])?, first_min.or(last_min), first_max.or(last_max))
+        },
+        _ => return None
+    };
+    return Some(MediaFeature::Range {name, min, max});
+}
+
+// The operator that compares the same way with the operands swapped.
+fn flipped(operator : &str) -> &str {
+    return match operator {
+        "<" => ">",
+        "<=" => ">=",
+        ">" => "<",
+        ">=" => "<=",
+        other => other
+    }
+}
+
+fn is_identifier(text : &str) -> bool {
+    let starts_well = text.starts_with(|c : char| !c.is_ascii_digit());
+    return starts_well && text.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bound(px : f32, inclusive : bool) -> Option<MediaBound> {
+        return Some(MediaBound {length: Length::Px(px), inclusive});
+    }
+
+    fn feature(query : &str) -> MediaFeature {
+        return parse_media_queries(query)[0].conditions[0].clone();
+    }
+
+    #[test]
+    fn parses_media_types_and_features() {
+        let queries = parse_media_queries("not screen and (min-width: 600px), print");
+        assert_eq!(queries.len(), 2);
+        assert!(queries[0].negated);
+        assert_eq!(queries[0].media_type.as_deref(), Some("screen"));
+        assert_eq!(queries[0].conditions, vec![MediaFeature::Range {name: "width".to_string(), min: bound(600.0, true), max: None}]);
+        assert_eq!(queries[1].media_type.as_deref(), Some("print"));
+
+        assert_eq!(feature("(orientation: Landscape)"), MediaFeature::Plain {name: "orientation".to_string(), value: Some("landscape".to_string())});
+        assert_eq!(feature("(color)"), MediaFeature::Plain {name: "color".to_string(), value: None});
+        assert_eq!(parse_media_queries("screen and, print").len(), 1);
+        assert!(parse_media_queries("screen and").is_empty());
+        assert!(parse_media_queries("screen print").is_empty());
+    }
+
+    #[test]
+    fn parses_range_syntax() {
+        assert_eq!(feature("(width >= 600px)"), MediaFeature::Range {name: "width".to_string(), min: bound(600.0, true), max: None});
+        assert_eq!(feature("(600px > height)"), MediaFeature::Range {name: "height".to_string(), min: None, max: bound(600.0, false)});
+        assert_eq!(feature("(400px < width <= 900px)"), MediaFeature::Range {name: "width".to_string(), min: bound(400.0, false), max: bound(900.0, true)});
+        assert_eq!(feature("(width = 30em)"), MediaFeature::Range {
+            name: "width".to_string(),
+            min: Some(MediaBound {length: Length::Em(30.0), inclusive: true}),
+            max: Some(MediaBound {length: Length::Em(30.0), inclusive: true}),
+        });
+        assert!(parse_media_queries("(400px < width > 900px)").is_empty());
+        assert!(parse_media_queries("(width >= auto)").is_empty());
+    }
+
+    #[test]
+    fn matches_the_viewport() {
+        let matches = |query : &str, viewport : (f32, f32)| parse_media_queries(query)[0].matches(viewport);
+        assert!(matches("(min-width: 600px)", (600.0, 100.0)));
+        assert!(!matches("(width > 600px)", (600.0, 100.0)));
+        assert!(matches("screen and (400px < width < 900px)", (500.0, 100.0)));
+        assert!(matches("(max-width: 40em)", (640.0, 100.0)));
+        assert!(!matches("print", (640.0, 100.0)));
+        assert!(matches("not print", (640.0, 100.0)));
+        assert!(matches("(orientation: portrait)", (100.0, 100.0)));
+        assert!(!matches("(min-width: 50%)", (640.0, 100.0)));
+        assert!(!matches("(hover)", (640.0, 100.0)));
+    }
+}
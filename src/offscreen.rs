@@ -0,0 +1,111 @@
+use layout::layout;
+use paint::{Vertex, clear_color, paint_boxes};
+use parse::CssRoot;
+
+// Paints `root` like the window would, without one: it is laid out for a `width` by
+// `height` viewport and the boxes are drawn in software into a target of that size,
+// cleared to `clear_color`. The pixels come back as RGBA bytes, row by row from the
+// top, ready for `RgbaImage::from_raw`.
+pub fn render_offscreen(root : &CssRoot, width : u32, height : u32) -> Vec<u8> {
+    let clear = clear_color(root);
+    let mut target = vec![clear; width as usize * height as usize];
+    let boxes = layout(root, (width as f32, height as f32));
+    for triangle in paint_boxes(&boxes).chunks_exact(3) {
+        fill_triangle(&mut target, width, height, triangle);
+    }
+
+    return target.iter()
+        .flat_map(|pixel| pixel.iter().map(|&channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8))
+        .collect();
+}
+
+// Fills the pixels whose centers fall inside the triangle, blending the interpolated
+// vertex colors over what is there by their alpha. Pixels on an edge shared by two
+// triangles are only filled by one of them, as on the GPU, so translucent rects are
+// blended once.
+fn fill_triangle(target : &mut [[f32; 4]], width : u32, height : u32, triangle : &[Vertex]) {
+    let (a, mut b, mut c) = (triangle[0], triangle[1], triangle[2]);
+    let mut area = edge(a.pos, b.pos, c.pos);
+    if area == 0.0 {
+        return;
+    }
+    if area < 0.0 {
+        std::mem::swap(&mut b, &mut c);
+        area = -area;
+    }
+
+    let xs = [a.pos[0], b.pos[0], c.pos[0]];
+    let ys = [a.pos[1], b.pos[1], c.pos[1]];
+    let min = |v : [f32; 3]| v[0].min(v[1]).min(v[2]);
+    let max = |v : [f32; 3]| v[0].max(v[1]).max(v[2]);
+    let (left, right) = (pixel_start(min(xs), width), pixel_start(max(xs) + 1.0, width));
+    let (top, bottom) = (pixel_start(min(ys), height), pixel_start(max(ys) + 1.0, height));
+
+    let edges = [(b.pos, c.pos), (c.pos, a.pos), (a.pos, b.pos)];
+    for y in top..bottom {
+        for x in left..right {
+            let center = [x as f32 + 0.5, y as f32 + 0.5];
+            let mut weights = [0.0; 3];
+            let mut inside = true;
+            for (weight, &(from, to)) in weights.iter_mut().zip(edges.iter()) {
+                *weight = edge(from, to, center);
+                inside &= *weight > 0.0 || (*weight == 0.0 && is_top_left(from, to));
+            }
+            if !inside {
+                continue;
+            }
+
+            let mix = |i : usize| (a.color[i] * weights[0] + b.color[i] * weights[1] + c.color[i] * weights[2]) / area;
+            let pixel = &mut target[y * width as usize + x];
+            let alpha = mix(3);
+            for (i, channel) in pixel.iter_mut().enumerate().take(3) {
+                *channel = mix(i) * alpha + *channel * (1.0 - alpha);
+            }
+            pixel[3] = alpha + pixel[3] * (1.0 - alpha);
+        }
+    }
+}
+
+// Positive when `point` is to the right of the line from `from` to `to`, with y pointing
+// down, and twice the area of the triangle they make.
+fn edge(from : [f32; 2], to : [f32; 2], point : [f32; 2]) -> f32 {
+    return (to[0] - from[0]) * (point[1] - from[1]) - (to[1] - from[1]) * (point[0] - from[0]);
+}
+
+// For triangles wound so that `edge` is positive inside, the top edges run to the right
+// and the left edges run up.
+fn is_top_left(from : [f32; 2], to : [f32; 2]) -> bool {
+    return (from[1] == to[1] && to[0] > from[0]) || to[1] < from[1];
+}
+
+// The first pixel at or after `coordinate`, clamped to the target.
+fn pixel_start(coordinate : f32, size : u32) -> usize {
+    return coordinate.floor().clamp(0.0, size as f32) as usize;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(pixels : &[u8], width : u32, x : u32, y : u32) -> &[u8] {
+        let i = (y * width + x) as usize * 4;
+        return &pixels[i..i + 4];
+    }
+
+    #[test]
+    fn renders_percentage_layouts() {
+        let root = ::parse_str(include_str!("../examples/two_column.css")).unwrap();
+        let pixels = render_offscreen(&root, 100, 50);
+        assert_eq!(pixels.len(), 100 * 50 * 4);
+        assert_eq!(pixel(&pixels, 100, 10, 25), &[0x30, 0x60, 0xa0, 0xff]);
+        assert_eq!(pixel(&pixels, 100, 80, 25), &[0xe0, 0xe0, 0xe0, 0xff]);
+    }
+
+    #[test]
+    fn renders_margins_where_layout_puts_them() {
+        let root = ::parse_str("a { width: 10px; height: 10px; margin: 5px; background-color: red }").unwrap();
+        let pixels = render_offscreen(&root, 20, 20);
+        assert_eq!(pixel(&pixels, 20, 2, 2), &[0x1a, 0x33, 0x4d, 0xff]);
+        assert_eq!(pixel(&pixels, 20, 7, 7), &[0xff, 0x00, 0x00, 0xff]);
+    }
+}
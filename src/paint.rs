@@ -0,0 +1,362 @@
+use cgmath::{Matrix4, Vector4, ortho};
+
+use cascade::computed;
+use color::parse_color;
+use gradient::Gradient;
+use layout::{LayoutBox, Rect, layout_hovered};
+use parse::CssRoot;
+use transform::{Transform, transform_matrix};
+
+pub const CLEAR_COLOR: [f32; 4] = [0.1, 0.2, 0.3, 1.0];
+
+// Triangles per rounded corner when painting `border-radius`.
+const CORNER_SEGMENTS: u32 = 8;
+
+// CPU-side geometry for the triangle_150 shaders: `pos` feeds `a_Pos` and
+// `color` feeds `a_Color`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vertex {
+    pub pos: [f32; 2],
+    pub color: [f32; 4],
+}
+
+// Two triangles covering the axis-aligned rectangle with its corner at (x, y).
+pub fn rect_vertices(x : f32, y : f32, w : f32, h : f32, color : [f32; 4]) -> [Vertex; 6] {
+    let vertex = |px : f32, py : f32| Vertex {pos: [px, py], color};
+    return [
+        vertex(x, y),
+        vertex(x + w, y),
+        vertex(x + w, y + h),
+        vertex(x, y),
+        vertex(x + w, y + h),
+        vertex(x, y + h),
+    ];
+}
+
+// A triangle fan around the center of `rect`, as a triangle list, with each corner
+// approximated by `segments` straight edges. The radius is clamped so opposite
+// corners never overlap; a zero radius gives the plain two-triangle rect.
+pub fn rounded_rect_vertices(rect : Rect, radius : f32, color : [f32; 4], segments : u32) -> Vec<Vertex> {
+    let radius = radius.min(rect.width.min(rect.height) / 2.0);
+    if radius <= 0.0 {
+        return rect_vertices(rect.x, rect.y, rect.width, rect.height, color).to_vec();
+    }
+
+    let (left, top) = (rect.x + radius, rect.y + radius);
+    let (right, bottom) = (rect.x + rect.width - radius, rect.y + rect.height - radius);
+    // Clockwise from the top-left corner, with y pointing down.
+    let corners = [(left, top, 180.0f32), (right, top, 270.0), (right, bottom, 0.0), (left, bottom, 90.0)];
+
+    let segments = segments.max(1);
+    let mut outline = Vec::new();
+    for &(cx, cy, start) in corners.iter() {
+        for i in 0..=segments {
+            let angle = (start + 90.0 * i as f32 / segments as f32).to_radians();
+            outline.push([cx + radius * angle.cos(), cy + radius * angle.sin()]);
+        }
+    }
+
+    let center = Vertex {pos: [rect.x + rect.width / 2.0, rect.y + rect.height / 2.0], color};
+    let mut vertices = Vec::with_capacity(outline.len() * 3);
+    for (i, &pos) in outline.iter().enumerate() {
+        let next = outline[(i + 1) % outline.len()];
+        vertices.extend_from_slice(&[center, Vertex {pos, color}, Vertex {pos: next, color}]);
+    }
+    return vertices;
+}
+
+// Cuts `rect` into one band per pair of neighbouring stops, across the gradient line,
+// and colors each corner by its position so the GPU blends between the stops. The
+// line runs through the center and is just long enough for the corners to reach
+// its ends, as in CSS.
+pub fn gradient_vertices(rect : Rect, gradient : &Gradient) -> Vec<Vertex> {
+    let angle = gradient.angle.to_radians();
+    let direction = [angle.sin(), -angle.cos()];
+    let length = (rect.width * direction[0]).abs() + (rect.height * direction[1]).abs();
+    let center = [rect.x + rect.width / 2.0, rect.y + rect.height / 2.0];
+    let along = |[x, y] : [f32; 2]| {
+        if length == 0.0 {
+            return 0.0;
+        }
+        return ((x - center[0]) * direction[0] + (y - center[1]) * direction[1]) / length + 0.5;
+    };
+
+    let corners = vec![
+        [rect.x, rect.y],
+        [rect.x + rect.width, rect.y],
+        [rect.x + rect.width, rect.y + rect.height],
+        [rect.x, rect.y + rect.height],
+    ];
+
+    let mut bounds: Vec<f32> = gradient.stops.iter().map(|&(_, position)| position.clamp(0.0, 1.0)).collect();
+    bounds.insert(0, 0.0);
+    bounds.push(1.0);
+
+    let mut vertices = Vec::new();
+    for band in bounds.windows(2).filter(|band| band[1] > band[0]) {
+        let outline = clip(&clip(&corners, &|pos| along(pos) - band[0]), &|pos| band[1] - along(pos));
+        let vertex = |pos : [f32; 2]| Vertex {pos, color: gradient.color_at(along(pos))};
+        for i in 1..outline.len().saturating_sub(1) {
+            vertices.extend_from_slice(&[vertex(outline[0]), vertex(outline[i]), vertex(outline[i + 1])]);
+        }
+    }
+    return vertices;
+}
+
+// The part of a convex polygon where `side` is not negative.
+fn clip(polygon : &[[f32; 2]], side : &dyn Fn([f32; 2]) -> f32) -> Vec<[f32; 2]> {
+    let mut clipped = Vec::new();
+    for (i, &from) in polygon.iter().enumerate() {
+        let to = polygon[(i + 1) % polygon.len()];
+        let (a, b) = (side(from), side(to));
+        if a >= 0.0 {
+            clipped.push(from);
+        }
+        if (a >= 0.0) != (b >= 0.0) {
+            let t = a / (a - b);
+            clipped.push([from[0] + (to[0] - from[0]) * t, from[1] + (to[1] - from[1]) * t]);
+        }
+    }
+    return clipped;
+}
+
+// One rectangle per side with a width, filling the gap between the border and padding
+// boxes. The top and bottom sides span the full width, so corners are drawn twice.
+pub fn border_vertices(layout_box : &LayoutBox) -> Vec<Vertex> {
+    let (border, padding) = (layout_box.border, layout_box.padding);
+    let top = padding.y - border.y;
+    let right = (border.x + border.width) - (padding.x + padding.width);
+    let bottom = (border.y + border.height) - (padding.y + padding.height);
+    let left = padding.x - border.x;
+
+    let sides = [
+        (border.x, border.y, border.width, top),
+        (border.x + border.width - right, border.y, right, border.height),
+        (border.x, border.y + border.height - bottom, border.width, bottom),
+        (border.x, border.y, left, border.height),
+    ];
+
+    let mut vertices = Vec::new();
+    for (&(x, y, w, h), color) in sides.iter().zip(layout_box.border_colors.iter()) {
+        if w > 0.0 && h > 0.0 {
+            vertices.extend_from_slice(&rect_vertices(x, y, w, h, color.to_rgba_f32()));
+        }
+    }
+    return vertices;
+}
+
+// Paints laid out boxes in the order given, see `layout_box_vertices`.
+pub fn paint_boxes(boxes : &[LayoutBox]) -> Vec<Vertex> {
+    return boxes.iter().flat_map(layout_box_vertices).collect();
+}
+
+// The background of a box fills its border box, rounded by `border_radius` unless it is
+// a gradient, and its borders go over the edges of it. Both take the opacity of the box
+// and are transformed about its center.
+fn layout_box_vertices(layout_box : &LayoutBox) -> Vec<Vertex> {
+    let rect = layout_box.border;
+    // Gradients are painted square-cornered for now.
+    let mut vertices = match (layout_box.background_gradient.as_ref(), layout_box.background) {
+        (Some(gradient), _) => gradient_vertices(rect, gradient),
+        (None, Some(background)) => {
+            rounded_rect_vertices(rect, layout_box.border_radius, background.to_rgba_f32(), CORNER_SEGMENTS)
+        },
+        (None, None) => Vec::new()
+    };
+    vertices.extend(border_vertices(layout_box));
+
+    for vertex in vertices.iter_mut() {
+        vertex.color = apply_opacity(vertex.color, layout_box.opacity);
+    }
+    apply_transform(&mut vertices, &layout_box.transforms, rect);
+    return vertices;
+}
+
+// Maps window pixels to clip space with the origin in the top-left corner, matching
+// CSS coordinates. Needs recomputing whenever the window is resized.
+pub fn ortho_for(width : u32, height : u32) -> Matrix4<f32> {
+    return ortho(0.0, width as f32, height as f32, 0.0, -1.0, 1.0);
+}
+
+// The window background, taken from the `background-color` of `body`.
+pub fn clear_color(root : &CssRoot) -> [f32; 4] {
+    return computed(root, "body").get("background-color")
+        .and_then(|value| parse_color(value))
+        .map(|color| color.to_rgba_f32())
+        .unwrap_or(CLEAR_COLOR);
+}
+
+// Lays `root` out for a window of `viewport` size and paints the boxes into a single
+// vertex list, in `z-index` order. See `layout` for what makes a box.
+pub fn paint(root : &CssRoot, viewport : (f32, f32)) -> Vec<Vertex> {
+    return paint_hovered(root, viewport, None);
+}
+
+// As `paint`, with the `:hover` rule sets applied to the rule set whose selectors are
+// `hovered`, like those of the box under the cursor.
+pub fn paint_hovered(root : &CssRoot, viewport : (f32, f32), hovered : Option<&[String]>) -> Vec<Vertex> {
+    return paint_boxes(&layout_hovered(root, viewport, hovered));
+}
+
+// Transforms the vertices of a box about the center of `rect`, its border box.
+fn apply_transform(vertices : &mut [Vertex], transforms : &[Transform], rect : Rect) {
+    if transforms.is_empty() {
+        return;
+    }
+
+    let matrix = transform_matrix(transforms, [rect.x + rect.width / 2.0, rect.y + rect.height / 2.0]);
+    for vertex in vertices.iter_mut() {
+        let pos = matrix * Vector4::new(vertex.pos[0], vertex.pos[1], 0.0, 1.0);
+        vertex.pos = [pos.x, pos.y];
+    }
+}
+
+pub fn apply_opacity(color : [f32; 4], opacity : f32) -> [f32; 4] {
+    let [r, g, b, a] = color;
+    return [r, g, b, a * opacity.clamp(0.0, 1.0)];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use layout::layout;
+
+    fn parse(css : &str) -> CssRoot {
+        return ::parse_str(css).unwrap();
+    }
+
+    #[test]
+    fn paint_boxes_keeps_opacity() {
+        let root = parse("a { width: 10px; height: 10px; background-color: red; opacity: 0.5 }");
+        let vertices = paint_boxes(&layout(&root, (100.0, 100.0)));
+        assert_eq!(vertices.len(), 6);
+        assert!(vertices.iter().all(|vertex| vertex.color[3] == 0.5));
+    }
+
+    #[test]
+    fn paint_boxes_keeps_radius_and_gradients() {
+        let rounded = parse("a { width: 40px; height: 20px; background-color: blue; border-radius: 4px }");
+        assert_eq!(paint_boxes(&layout(&rounded, (100.0, 100.0))).len(), 4 * (CORNER_SEGMENTS as usize + 1) * 3);
+
+        let gradient = parse("a { width: 40px; height: 20px; background: linear-gradient(90deg, red, blue) }");
+        let vertices = paint_boxes(&layout(&gradient, (100.0, 100.0)));
+        assert_eq!(vertices[0].color, [1.0, 0.0, 0.0, 1.0]);
+        assert!(vertices.iter().any(|vertex| vertex.color == [0.0, 0.0, 1.0, 1.0]));
+    }
+
+    #[test]
+    fn paint_draws_borders() {
+        let root = parse("a { width: 10px; height: 10px; border-color: blue; border: 2px solid red }");
+        let vertices = paint(&root, (100.0, 100.0));
+        assert_eq!(vertices.len(), 4 * 6);
+        assert!(vertices.iter().all(|vertex| vertex.color == [1.0, 0.0, 0.0, 1.0]));
+    }
+
+    #[test]
+    fn paint_follows_layout() {
+        let root = parse("a { left: 10px; top: 10px; width: 100px; height: 50px; margin: 20px; background-color: red }");
+        let vertices = paint(&root, (200.0, 200.0));
+        assert_eq!(vertices[0].pos, [30.0, 30.0]);
+        assert_eq!(vertices[2].pos, [130.0, 80.0]);
+    }
+
+    #[test]
+    fn paint_hovered_applies_hover_rules() {
+        let root = parse("a { width: 10px; height: 10px; background-color: red } a:hover { background-color: blue }");
+        let selectors = ["a".to_string()];
+        assert_eq!(paint(&root, (100.0, 100.0))[0].color, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(paint_hovered(&root, (100.0, 100.0), Some(&selectors))[0].color, [0.0, 0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn paint_transforms_about_the_center() {
+        let plain = paint(&parse("a { width: 10px; height: 10px; background-color: red }"), (100.0, 100.0));
+        let moved = paint(&parse("a { width: 10px; height: 10px; background-color: red; transform: translate(5px, 6px) }"), (100.0, 100.0));
+        for (plain, moved) in plain.iter().zip(moved.iter()) {
+            assert_eq!([plain.pos[0] + 5.0, plain.pos[1] + 6.0], moved.pos);
+        }
+
+        let scaled = paint(&parse("a { width: 10px; height: 10px; background-color: red; transform: scale(2) }"), (100.0, 100.0));
+        assert_eq!(scaled[0].pos, [-5.0, -5.0]);
+    }
+
+    #[test]
+    fn gradient_bands_cover_the_rect() {
+        let gradient = ::parse_gradient("linear-gradient(to right, red, blue)").unwrap();
+        let vertices = gradient_vertices(Rect::new(0.0, 0.0, 100.0, 50.0), &gradient);
+        assert_eq!(vertices.len(), 6);
+        for vertex in vertices.iter() {
+            let expected = if vertex.pos[0] == 0.0 { [1.0, 0.0, 0.0, 1.0] } else { [0.0, 0.0, 1.0, 1.0] };
+            assert_eq!(vertex.color, expected);
+        }
+
+        let gradient = ::parse_gradient("linear-gradient(30deg, red, lime, blue)").unwrap();
+        let vertices = gradient_vertices(Rect::new(0.0, 0.0, 100.0, 60.0), &gradient);
+        let area: f32 = vertices.chunks(3).map(|t| {
+            ((t[1].pos[0] - t[0].pos[0]) * (t[2].pos[1] - t[0].pos[1]) - (t[2].pos[0] - t[0].pos[0]) * (t[1].pos[1] - t[0].pos[1])).abs() / 2.0
+        }).sum();
+        assert!((area - 6000.0).abs() < 0.1, "{}", area);
+    }
+
+    #[test]
+    fn rect_vertices_cover_the_rect() {
+        let color = [1.0, 0.0, 0.0, 1.0];
+        let positions: Vec<_> = rect_vertices(1.0, 2.0, 3.0, 4.0, color).iter().map(|vertex| vertex.pos).collect();
+        assert_eq!(positions, vec![[1.0, 2.0], [4.0, 2.0], [4.0, 6.0], [1.0, 2.0], [4.0, 6.0], [1.0, 6.0]]);
+        assert!(rect_vertices(1.0, 2.0, 3.0, 4.0, color).iter().all(|vertex| vertex.color == color));
+    }
+
+    #[test]
+    fn ortho_maps_pixels_to_clip_space() {
+        let projection = ortho_for(200, 100);
+        let clip = |x : f32, y : f32| {
+            let pos = projection * Vector4::new(x, y, 0.0, 1.0);
+            [pos.x, pos.y]
+        };
+        assert_eq!(clip(0.0, 0.0), [-1.0, 1.0]);
+        assert_eq!(clip(100.0, 50.0), [0.0, 0.0]);
+        assert_eq!(clip(200.0, 100.0), [1.0, -1.0]);
+    }
+
+    #[test]
+    fn clear_color_comes_from_body() {
+        assert_eq!(clear_color(&parse("body { background-color: #ff0000 } body { background-color: lime }")), [0.0, 1.0, 0.0, 1.0]);
+        assert_eq!(clear_color(&parse("body { background-color: nonsense }")), CLEAR_COLOR);
+        assert_eq!(clear_color(&parse("a { background-color: red }")), CLEAR_COLOR);
+    }
+
+    #[test]
+    fn opacity_scales_alpha() {
+        assert_eq!(apply_opacity([1.0, 0.5, 0.0, 0.5], 0.5), [1.0, 0.5, 0.0, 0.25]);
+        assert_eq!(apply_opacity([1.0, 0.5, 0.0, 0.5], 2.0), [1.0, 0.5, 0.0, 0.5]);
+        assert_eq!(apply_opacity([1.0, 0.5, 0.0, 0.5], -1.0), [1.0, 0.5, 0.0, 0.0]);
+
+        let root = parse("a { width: 10px; height: 10px; background-color: red; opacity: 25% }");
+        assert_eq!(paint(&root, (100.0, 100.0))[0].color[3], 0.25);
+    }
+
+    #[test]
+    fn rounded_rects_stay_inside_the_rect() {
+        let rect = Rect::new(10.0, 10.0, 40.0, 20.0);
+        let color = [1.0, 1.0, 1.0, 1.0];
+        assert_eq!(rounded_rect_vertices(rect, 0.0, color, 8), rect_vertices(10.0, 10.0, 40.0, 20.0, color).to_vec());
+
+        // The radius is clamped to half the shorter side, so the left and right edges are half circles.
+        let vertices = rounded_rect_vertices(rect, 50.0, color, 4);
+        assert_eq!(vertices.len(), 4 * 5 * 3);
+        for vertex in vertices.iter() {
+            let [x, y] = vertex.pos;
+            assert!((10.0 - 1e-4..=50.0 + 1e-4).contains(&x) && (10.0 - 1e-4..=30.0 + 1e-4).contains(&y), "{:?}", vertex.pos);
+        }
+        assert!(vertices.iter().any(|vertex| (vertex.pos[0] - 10.0).abs() < 1e-4 && (vertex.pos[1] - 20.0).abs() < 1e-4));
+    }
+
+    #[test]
+    fn paints_by_z_index() {
+        let root = parse("a { width: 10px; height: 10px; background-color: red; z-index: 2 } \
+            b { width: 10px; height: 10px; background-color: lime; z-index: auto } \
+            c { width: 10px; height: 10px; background-color: blue }");
+        let colors: Vec<_> = paint(&root, (100.0, 100.0)).chunks(6).map(|rect| rect[0].color).collect();
+        assert_eq!(colors, vec![[0.0, 1.0, 0.0, 1.0], [0.0, 0.0, 1.0, 1.0], [1.0, 0.0, 0.0, 1.0]]);
+    }
+}
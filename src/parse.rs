@@ -1,331 +1,1261 @@
-use std::error::Error;
-use std::fs::File;
-use std::io::prelude::*;
-use std::path::Path;
+use std::borrow::Cow;
 use std::mem;
 
-fn main() {
-    let path = Path::new("example.css");
-    let file_path_display = path.display();
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-    let mut file = match File::open(&path) {
-        Err(why) => panic!("couldn't open {}: {}", file_path_display, why.description()),
-        Ok(file) => file,
-    };
+use error::CssError;
+use media::{MediaQuery, parse_media_queries};
+use token::{TokenKind, spanned_tokens};
+use value::{self, parse_value, split_value_list};
 
-    let mut s = String::new();
-    let file_contents = match file.read_to_string(&mut s) {
-        Err(why) => panic!("couldn't read {}: {}", file_path_display,
-                           why.description()),
-        Ok(_) => s,
-    };
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CssRoot {
+    pub rule_sets : Vec<CssRuleSet>,
+    pub media_queries : Vec<CssMediaQuery>,
+    pub charset : Option<String>,
+    pub namespaces : Vec<(Option<String>, String)>,
+    pub imports : Vec<String>,
+    pub font_faces : Vec<CssFontFace>,
+    pub keyframes : Vec<CssKeyframes>,
+}
+impl CssRoot {
+    pub fn new() -> CssRoot {
+        return CssRoot {
+            rule_sets: Vec::new(),
+            media_queries: Vec::new(),
+            charset: None,
+            namespaces: Vec::new(),
+            imports: Vec::new(),
+            font_faces: Vec::new(),
+            keyframes: Vec::new()
+        }
+    }
+
+    // Top-level rule sets listing `selector` in their group, compared as exact strings for now.
+    pub fn select(&self, selector : &str) -> Vec<&CssRuleSet> {
+        return self.rule_sets.iter()
+            .filter(|rule_set| rule_set.selectors.iter().any(|s| s == selector))
+            .collect();
+    }
 
-    let mut parser = CssParser::new();
-    parser.parse(file_contents);
+    // Every declaration paired with its rule set, including those nested in media queries.
+    pub fn rules<'a>(&'a self) -> impl Iterator<Item = (&'a CssRuleSet, &'a CssRule)> + 'a {
+        let media_rule_sets = self.media_queries.iter().flat_map(|m| m.rule_sets.iter());
+        return self.rule_sets.iter()
+            .chain(media_rule_sets)
+            .flat_map(|rule_set| rule_set.rules.iter().map(move |rule| (rule_set, rule)));
+    }
+
+    // Layers `other` over this stylesheet, as if it came after it in the same file, so
+    // its rules win where specificity ties. Resolve custom properties after merging so
+    // a theme's `:root` overrides the base's. The first `@charset` is kept.
+    pub fn merge(&mut self, other : CssRoot) {
+        self.charset = self.charset.take().or(other.charset);
+        self.namespaces.extend(other.namespaces);
+        self.imports.extend(other.imports);
+        self.rule_sets.extend(other.rule_sets);
+        self.media_queries.extend(other.media_queries);
+        self.font_faces.extend(other.font_faces);
+        self.keyframes.extend(other.keyframes);
+    }
+
+    // Equality that doesn't depend on formatting, for comparing a stylesheet with what it
+    // prints to or what it was merged from. The order of the selectors in a group doesn't
+    // count, nor how much whitespace separates the parts of a selector, value, media
+    // condition or keyframe stop, nor the source rule sets were read from. The order of
+    // rule sets, declarations and everything else still has to match.
+    pub fn semantically_eq(&self, other : &CssRoot) -> bool {
+        let media_eq = |a : &CssMediaQuery, b : &CssMediaQuery| {
+            collapse_whitespace(&a.condition) == collapse_whitespace(&b.condition)
+                && rule_sets_eq(&a.rule_sets, &b.rule_sets)
+        };
+        let font_faces_eq = |a : &CssFontFace, b : &CssFontFace| rules_eq(&a.descriptors, &b.descriptors);
+        let keyframes_eq = |a : &CssKeyframes, b : &CssKeyframes| {
+            a.name == b.name && all_eq(&a.frames, &b.frames, |a, b| {
+                collapse_whitespace(&a.0) == collapse_whitespace(&b.0) && rules_eq(&a.1, &b.1)
+            })
+        };
+
+        return self.charset == other.charset
+            && self.namespaces == other.namespaces
+            && self.imports == other.imports
+            && rule_sets_eq(&self.rule_sets, &other.rule_sets)
+            && all_eq(&self.media_queries, &other.media_queries, media_eq)
+            && all_eq(&self.font_faces, &other.font_faces, font_faces_eq)
+            && all_eq(&self.keyframes, &other.keyframes, keyframes_eq);
+    }
 }
 
-trait Css {
-    fn test(&self, char : char) -> CssTestResult;
-    fn begin(&self) {}
-    fn append(&self) {}
-    fn end(&self) {}
+fn all_eq<T, F: Fn(&T, &T) -> bool>(a : &[T], b : &[T], eq : F) -> bool {
+    return a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| eq(a, b));
 }
 
-#[derive(Debug)]
-struct CssNone { }
-impl CssNone {
-    fn new() -> CssNone {
-        return CssNone {}
+fn rule_sets_eq(a : &[CssRuleSet], b : &[CssRuleSet]) -> bool {
+    let selectors = |rule_set : &CssRuleSet| {
+        let mut selectors: Vec<_> = rule_set.selectors.iter().map(|s| collapse_whitespace(s)).collect();
+        selectors.sort();
+        selectors
+    };
+    return all_eq(a, b, |a, b| {
+        selectors(a) == selectors(b) && rules_eq(&a.rules, &b.rules) && rule_sets_eq(&a.children, &b.children)
+    });
+}
+
+// The typed values follow from the raw ones, so only those are compared.
+fn rules_eq(a : &[CssRule], b : &[CssRule]) -> bool {
+    return all_eq(a, b, |a, b| {
+        a.key == b.key && collapse_whitespace(&a.value) == collapse_whitespace(&b.value)
+            && a.important == b.important && a.flags == b.flags
+    });
+}
+// Accepts both `"theme.css"` and `url(theme.css)`, ignoring anything after it like
+// the media list of an `@import`.
+fn url_argument(params : &str) -> Option<&str> {
+    let is_url = params.get(..4).is_some_and(|p| p.eq_ignore_ascii_case("url("));
+    let url = if is_url { params[4..].trim_start() } else { params };
+
+    let quote = url.chars().next()?;
+    if quote == '"' || quote == '\'' {
+        let end = unescaped_find(&url[1..], quote)?;
+        return Some(&url[1..end + 1]);
     }
+
+    let url = if is_url { url[..unescaped_find(url, ')')?].trim_end() } else { url };
+    // A quote can't start partway into an unquoted url.
+    if unescaped_find(url, '"').is_some() || unescaped_find(url, '\'').is_some() {
+        return None;
+    }
+    return Some(url);
 }
-impl Css for CssNone {
-    fn test(&self, css : char) -> CssTestResult {
-        match css {
-            _ => CssTestResult {context: CssContext::None, command: CssCommand::None},
+
+// The byte offset of the first `c` in `text` that isn't escaped by a backslash.
+pub(crate) fn unescaped_find(text : &str, c : char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, next) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if next == '\\' {
+            escaped = true;
+        } else if next == c {
+            return Some(i);
         }
     }
+    return None;
 }
 
-#[derive(Debug)]
-struct CssRoot {
-    rule_sets : Vec<CssRuleSet>,
+// Reads `svg url(http://www.w3.org/2000/svg)` as a prefix and uri, the prefix being an
+// optional identifier.
+fn namespace(params : &str) -> Option<(Option<String>, String)> {
+    let uri_start = params.get(..4).is_some_and(|p| p.eq_ignore_ascii_case("url("))
+        || params.starts_with('"') || params.starts_with('\'');
+    if uri_start {
+        return Some((None, url_argument(params)?.to_string()));
+    }
+
+    let end = params.find(char::is_whitespace)?;
+    let prefix = &params[..end];
+    if !prefix.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+        return None;
+    }
+
+    let uri = url_argument(params[end..].trim_start())?;
+    return Some((Some(prefix.to_string()), uri.to_string()));
 }
-impl CssRoot {
-    fn new() -> CssRoot {
-        return CssRoot {
-            rule_sets: Vec::new()
+
+const VENDOR_PREFIXES: &[&str] = &["-webkit-", "-moz-", "-ms-", "-o-"];
+
+// The name without its vendor prefix, so `-webkit-transition` gives `transition` and
+// `-moz-keyframes` gives `keyframes`. Anything else comes back as it is.
+pub fn unprefixed(name : &str) -> &str {
+    for prefix in VENDOR_PREFIXES.iter() {
+        let has_prefix = name.get(..prefix.len()).is_some_and(|start| start.eq_ignore_ascii_case(prefix));
+        if has_prefix && name.len() > prefix.len() {
+            return &name[prefix.len()..];
         }
     }
+    return name;
 }
-impl Css for CssRoot {
-    fn test(&self, css : char) -> CssTestResult {
-        match css {
-            _ => CssTestResult {context: CssContext::Selector, command: CssCommand::Begin},
-        }
+
+// Splits `@media screen` into its name and the remaining prelude.
+fn split_at_rule(prelude : &str) -> (&str, &str) {
+    let prelude = prelude.trim_start_matches('@');
+    let end = prelude.find(|c : char| c.is_whitespace() || c == '(' || c == '"' || c == '\'')
+        .unwrap_or(prelude.len());
+    // The end was trimmed with the rest of the prelude, short of any escaped space.
+    return (&prelude[..end], prelude[end..].trim_start());
+}
+
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CssMediaQuery {
+    pub condition : String,
+    // The condition broken into its comma separated queries, less any malformed ones.
+    pub queries : Vec<MediaQuery>,
+    pub rule_sets : Vec<CssRuleSet>,
+}
+impl CssMediaQuery {
+    pub fn new() -> CssMediaQuery {
+        return CssMediaQuery {condition: "".to_string(), queries: Vec::new(), rule_sets: Vec::new()}
     }
+
+    // Whether the rule sets apply to a screen of the given size, as when any one of the
+    // queries matches. An empty condition always does.
+    pub fn matches(&self, viewport : (f32, f32)) -> bool {
+        return self.condition.trim().is_empty() || self.queries.iter().any(|query| query.matches(viewport));
+    }
+}
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CssFontFace {
+    pub descriptors : Vec<CssRule>,
 }
+impl CssFontFace {
+    pub fn new() -> CssFontFace {
+        return CssFontFace {descriptors: Vec::new()}
+    }
 
-#[derive(Debug)]
-struct CssSelector {}
-impl CssSelector {
-    fn new() -> CssSelector {
-        return CssSelector {}
+    pub fn get(&self, key : &str) -> Option<&str> {
+        return self.descriptors.iter().rev()
+            .find(|rule| rule.key == key)
+            .map(|rule| rule.value.as_str());
     }
 }
-impl Css for CssSelector {
-    fn test(&self, css : char) -> CssTestResult {
-        match css {
-            '{' => CssTestResult {context: CssContext::None,     command: CssCommand::End},
-            ',' => CssTestResult {context: CssContext::Selector, command: CssCommand::Append},
-            _ =>   CssTestResult {context: CssContext::Selector, command: CssCommand::None},
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CssKeyframes {
+    pub name : String,
+    pub frames : Vec<(String, Vec<CssRule>)>,
+}
+impl CssKeyframes {
+    pub fn new() -> CssKeyframes {
+        return CssKeyframes {name: "".to_string(), frames: Vec::new()}
+    }
+}
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CssRuleSet {
+    pub selectors : Vec<String>,
+    pub rules : Vec<CssRule>,
+    pub children : Vec<CssRuleSet>,
+    // The name of the stylesheet and the line the selectors start on, for rule sets parsed
+    // with `CssParser::with_source` as `parse_many` does.
+    pub source : Option<(String, usize)>,
+}
+impl CssRuleSet {
+    pub fn new() -> CssRuleSet {
+        return CssRuleSet {selectors: Vec::new(), rules: Vec::new(), children: Vec::new(), source: None}
+    }
 
+    // Desugars nested rule sets into top-level ones, so `.card { & .title {} }` becomes
+    // `.card .title {}`. A child selector without `&` is taken as a descendant of its parent.
+    pub fn flatten(&self) -> Vec<CssRuleSet> {
+        let mut flat = Vec::new();
+        if !self.rules.is_empty() || self.children.is_empty() {
+            let mut rule_set = CssRuleSet::new();
+            rule_set.selectors = self.selectors.clone();
+            rule_set.rules = self.rules.clone();
+            rule_set.source = self.source.clone();
+            flat.push(rule_set);
         }
+
+        for child in self.children.iter() {
+            let mut combined = CssRuleSet::new();
+            combined.selectors = self.nested_selectors(child);
+            combined.rules = child.rules.clone();
+            combined.children = child.children.clone();
+            combined.source = child.source.clone();
+            flat.extend(combined.flatten());
+        }
+
+        return flat;
     }
 
-    fn begin(&self, state : &mut CssParser) {
-        let char = state.current_char;
-        state.push_char(char);
+    // Whether one of the selectors is `selector`, however much whitespace separates their
+    // parts, so `matches_simple("ul li")` finds `ul   li`.
+    pub fn matches_simple(&self, selector : &str) -> bool {
+        let selector = collapse_whitespace(selector);
+        return self.selectors.iter().any(|own| collapse_whitespace(own) == selector);
     }
 
-    fn end(&self, state : &mut CssParser) {
-        let chars = state.flush_char_buffer();
-        state.ruleset.selectors.push(chars);
+    // The selectors of `child` when written out at the top level.
+    pub(crate) fn nested_selectors(&self, child : &CssRuleSet) -> Vec<String> {
+        return self.selectors.iter()
+            .flat_map(|parent| child.selectors.iter().map(move |selector| {
+                if selector.contains('&') {
+                    selector.replace('&', parent)
+                } else {
+                    format!("{} {}", parent, selector)
+                }
+            }))
+            .collect();
+    }
 
-        state.push_context(CssContext::RuleSet);
+    pub fn get(&self, key : &str) -> Option<&str> {
+        return self.rules.iter().rev()
+            .find(|rule| rule.key == key)
+            .map(|rule| rule.value.as_str());
     }
 
-    fn append(&self, state : &mut CssParser) {
-        let chars = state.flush_char_buffer();
-        state.ruleset.selectors.push(chars);
+    pub fn get_all(&self, key : &str) -> Vec<&str> {
+        return self.rules.iter()
+            .filter(|rule| rule.key == key)
+            .map(|rule| rule.value.as_str())
+            .collect();
     }
-}
 
-#[derive(Debug)]
-struct CssRuleSet {
-    selectors : Vec<String>,
-    rules : Vec<CssRule>
-}
-impl CssRuleSet {
-    fn new() -> CssRuleSet {
-        return CssRuleSet {selectors: Vec::new(), rules: Vec::new()}
+    // Properties declared more than once. Repeating a property with a function or vendor
+    // prefixed value, like `background: red; background: linear-gradient(..)`, is taken as
+    // a deliberate fallback for older readers and not reported.
+    pub fn duplicate_properties(&self) -> Vec<String> {
+        let mut duplicates: Vec<String> = Vec::new();
+        for (i, rule) in self.rules.iter().enumerate() {
+            let overrides = self.rules[..i].iter()
+                .filter(|earlier| earlier.key == rule.key)
+                .any(|earlier| !is_fallback(&earlier.value, &rule.value));
+            if overrides && !duplicates.contains(&rule.key) {
+                duplicates.push(rule.key.clone());
+            }
+        }
+        return duplicates;
     }
 }
-impl Css for CssRuleSet {
-    fn test(&self, css : char) -> CssTestResult {
-        match css {
-            ' '  |
-            '\n' |
-            '\r' |
-            '\t' => CssTestResult {context: CssContext::RuleSet, command: CssCommand::None},
-            '}' =>  CssTestResult {context: CssContext::None,    command: CssCommand::End},
-            _ =>    CssTestResult {context: CssContext::Key,     command: CssCommand::Begin},
-        }
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CssRule {
+    pub key: String,
+    pub value: String,
+    pub parsed: value::CssValue,
+    pub important: bool,
+    // Trailing `!` flags other than `!important`, like the `default` of `!default`.
+    pub flags: Vec<String>,
+}
+impl CssRule {
+    // A declaration built in code, as in `CssRule::new("color", "red").important()`.
+    // `CssRule::default()` gives an empty one.
+    pub fn new(key : &str, value : &str) -> CssRule {
+        let mut rule = CssRule {key: key.to_string(), ..CssRule::default()};
+        rule.set_value(value);
+        return rule;
+    }
+
+    pub fn important(mut self) -> CssRule {
+        self.important = true;
+        return self;
+    }
+
+    // Adds a trailing flag other than `!important`, like `default` for `!default`.
+    pub fn flag(mut self, flag : &str) -> CssRule {
+        self.flags.push(flag.to_ascii_lowercase());
+        return self;
     }
 
-    fn end(&self, state : &mut CssParser) {
-        let current_rule_set = mem::replace(&mut state.ruleset, CssRuleSet::new());
-        state.root.rule_sets.push(current_rule_set);
+    // Replaces the raw value and keeps the typed value in step with it.
+    pub fn set_value(&mut self, value : &str) {
+        self.value = value.to_string();
+        self.parsed = parse_value(&self.key, value);
+    }
+
+    // The typed value, parsed once when the rule was read or last set.
+    pub fn parsed_value(&self) -> &value::CssValue {
+        return &self.parsed;
+    }
+
+    // The comma-separated items of a list value like a font stack.
+    pub fn values(&self) -> Vec<String> {
+        return split_value_list(&self.value);
+    }
+
+    // The quote character used when the whole value is a single quoted string.
+    pub fn quote(&self) -> Option<char> {
+        return value::quote(&self.value);
     }
 }
 
-#[derive(Debug)]
-struct CssRule {
-    key: String,
-    value: String
+fn is_fallback(earlier : &str, later : &str) -> bool {
+    let modern = |value : &str| value.contains('(') || value.starts_with('-');
+    return earlier != later && (modern(earlier) || modern(later));
 }
-impl CssRule {
-    fn new() -> CssRule {
-        return CssRule {key: "".to_string(), value: "".to_string()}
+
+// Splits on the commas that aren't escaped, quoted or inside brackets like those of
+// `:not(a, b)`, trimming each selector but keeping an escaped trailing space as in `a\ `.
+fn split_selectors(selectors : &str) -> Vec<String> {
+    let mut split = Vec::new();
+    let (mut start, mut kept, mut escaped) = (0, 0, false);
+    let (mut depth, mut quote) = (0usize, None);
+    for (i, c) in selectors.char_indices() {
+        if escaped {
+            escaped = false;
+            kept = i + c.len_utf8() - start;
+            continue;
+        }
+
+        match c {
+            '\\' => escaped = true,
+            _ if quote == Some(c) => quote = None,
+            _ if quote.is_some() => {},
+            '"' | '\'' => quote = Some(c),
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                split.push(trim_buffered(&selectors[start..i], kept));
+                start = i + 1;
+                kept = 0;
+            },
+            _ => {}
+        }
     }
+    split.push(trim_buffered(&selectors[start..], kept));
+
+    split.retain(|selector| !selector.is_empty());
+    return split;
 }
 
-#[derive(Debug)]
-struct CssKey {}
-impl CssKey {
-    fn new() -> CssKey {
-        return CssKey {}
+// The byte offset just past the char the last backslash escapes in `text`, if any.
+fn escaped_end(text : &str) -> Option<usize> {
+    let mut end = None;
+    let mut chars = text.char_indices();
+    while let Some((_, c)) = chars.next() {
+        if c == '\\' {
+            end = chars.next().map(|(i, escaped)| i + escaped.len_utf8()).or(end);
+        }
     }
+    return end;
 }
-impl Css for  CssKey {
-    fn test(&self, css : char) -> CssTestResult {
-        match css {
-            ':' => CssTestResult {context: CssContext::None, command: CssCommand::End},
-            _ => CssTestResult   {context: CssContext::Key,  command: CssCommand::None},
+
+fn collapse_whitespace(text : &str) -> String {
+    return text.split_whitespace().collect::<Vec<_>>().join(" ");
+}
+
+// Trims the whitespace the contexts skip, but never cuts into the first `kept` bytes. Other
+// spaces, like a vertical tab, start a selector as any other char does, so they are kept.
+fn trim_buffered(text : &str, kept : usize) -> String {
+    let skipped : &[char] = &[' ', '\n', '\r', '\t'];
+    let end = text.trim_end_matches(skipped).len().max(kept);
+    return text[..end].trim_start_matches(skipped).to_string();
+}
+
+// Splits trailing flags like `!important` and `!default` off a value, lowercased and in
+// source order.
+fn strip_flags(value : &str) -> (&str, Vec<String>) {
+    let escaped = |text : &str| text.chars().rev().take_while(|&c| c == '\\').count() % 2 == 1;
+    let mut value = value;
+    let mut flags = Vec::new();
+
+    while let Some(bang) = value.rfind('!') {
+        let flag = value[bang + 1..].trim();
+        let is_ident = flag.starts_with(|c : char| c.is_alphabetic() || c == '-' || c == '_')
+            && flag.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+        if !is_ident || escaped(&value[..bang]) {
+            break;
         }
+        flags.insert(0, flag.to_ascii_lowercase());
+        value = &value[..bang];
     }
 
-    fn begin(&self, state : &mut CssParser) {
-        let char = state.current_char;
-        state.push_char(char);
+    if flags.is_empty() {
+        return (value, flags);
     }
 
-    fn end(&self, state : &mut CssParser) {
-        state.rule.key = state.flush_char_buffer();
-        state.push_context(CssContext::Value);
+    // The space of an escape like `a\ !important` belongs to the value.
+    let mut end = value.trim_end().len();
+    if escaped(&value[..end]) {
+        end += value[end..].chars().next().map_or(0, char::len_utf8);
     }
+    return (&value[..end], flags);
 }
 
-#[derive(Debug)]
-struct CssValue {}
-impl CssValue {
-    fn new() -> CssValue {
-        return CssValue {}
-    }
-}
-impl Css for CssValue {
-    fn test(&self, css : char) -> CssTestResult {
-        match css {
-            '"' => CssTestResult {context: CssContext::String, command: CssCommand::Begin},
-            '\''=> CssTestResult {context: CssContext::String, command: CssCommand::Begin},
-            ';' => CssTestResult {context: CssContext::None,   command: CssCommand::End},
-            '}' => CssTestResult {context: CssContext::None,   command: CssCommand::EndKeepChar},
-            _ => CssTestResult   {context: CssContext::Value,  command: CssCommand::None},
+// Decodes CSS escapes: `\"` becomes `"`, `\41 ` becomes `A`, and an escaped newline is dropped.
+pub fn unescape(s : &str) -> String {
+    let mut out = String::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+
+        let mut hex = String::new();
+        while hex.len() < 6 && chars.peek().is_some_and(|c| c.is_ascii_hexdigit()) {
+            hex.push(chars.next().unwrap());
         }
-    }
 
-    fn begin(&self, state : &mut CssParser) {
-        let char = state.current_char;
-        state.push_char(char);
+        if !hex.is_empty() {
+            let code = u32::from_str_radix(&hex, 16).unwrap();
+            out.push(::std::char::from_u32(code).filter(|&c| c != '\0').unwrap_or('\u{FFFD}'));
+            if chars.peek().is_some_and(|c| c.is_whitespace()) {
+                chars.next();
+            }
+        } else {
+            match chars.next() {
+                Some('\n') | None => {},
+                Some(escaped) => out.push(escaped)
+            }
+        }
     }
 
-    fn end(&self, state : &mut CssParser) {
-        state.rule.value = state.flush_char_buffer();
+    return out;
+}
 
-        let current_rule = mem::replace(&mut state.rule, CssRule::new());
-        state.ruleset.rules.push(current_rule);
+// As the CSS syntax spec preprocesses input, `\r\n`, `\r` and form feeds all become `\n`,
+// so files with Windows line endings parse exactly like Unix ones.
+pub(crate) fn normalize_newlines<'a>(input : &'a str) -> Cow<'a, str> {
+    if !input.contains(['\r', '\x0c']) {
+        return Cow::Borrowed(input);
     }
+    return Cow::Owned(input.replace("\r\n", "\n").replace(['\r', '\x0c'], "\n"));
 }
 
-#[derive(Debug)]
-struct CssString {}
-impl CssString {
-    fn new() -> CssString {
-        return CssString {}
+// Deep enough for any real stylesheet, shallow enough that walking the nested rule sets
+// it allows can't overflow the stack.
+const DEFAULT_MAX_DEPTH: usize = 256;
+
+// Often enough for a smooth progress bar, rarely enough not to slow the parse down.
+const DEFAULT_PROGRESS_INTERVAL: usize = 64 * 1024;
+
+pub struct CssParser {
+    max_depth: usize,
+    progress_interval: usize,
+    // The name rule sets record as their source.
+    source: Option<String>,
+    // Where the last parse stopped, see `position`.
+    position: (usize, usize),
+}
+impl Default for CssParser {
+    fn default() -> CssParser {
+        return CssParser::new();
     }
 }
-impl Css for CssString {
-    fn test(&self, css : char) -> CssTestResult {
-        match css {
-            '"' |
-            '\'' => CssTestResult {context: CssContext::None,   command: CssCommand::EndIncludeChar},
-            _ =>    CssTestResult {context: CssContext::String, command: CssCommand::None},
+impl CssParser {
+
+    pub fn new() -> CssParser {
+        return CssParser {
+            max_depth: DEFAULT_MAX_DEPTH,
+            progress_interval: DEFAULT_PROGRESS_INTERVAL,
+            source: None,
+            position: (1, 0),
         }
     }
+
+    // Caps how many contexts, like blocks, rule sets and declarations, may be open at
+    // once. Deeper input fails with `CssError::DepthExceeded`.
+    pub fn with_max_depth(mut self, max_depth : usize) -> CssParser {
+        self.max_depth = max_depth;
+        return self;
+    }
+
+    // How many chars `parse_with_progress` parses between calls to its callback.
+    pub fn with_progress_interval(mut self, chars : usize) -> CssParser {
+        self.progress_interval = chars.max(1);
+        return self;
+    }
+
+    // Records `name` and the line as the `source` of every rule set parsed.
+    pub fn with_source(mut self, name : &str) -> CssParser {
+        self.source = Some(name.to_string());
+        return self;
+    }
+
+    // The line and column the last parse stopped at, as its errors report them.
+    pub fn position(&self) -> (usize, usize) {
+        return self.position;
+    }
+
+    pub fn parse(&mut self, css : String) -> Result<CssRoot, CssError> {
+        return self.parse_bytes(&css);
+    }
+
+    // Builds the tree from the tokens of `tokenize`. Delimiters like `{`, `:` and `;` move
+    // between contexts, while whitespace, names, numbers and strings are buffered or skipped
+    // whole by the context they turn up in. Comments are dropped.
+    pub fn parse_bytes(&mut self, input : &str) -> Result<CssRoot, CssError> {
+        return self.parse_with_progress(input, |_| {});
+    }
+
+    // As `parse_bytes`, calling `progress` with how many bytes in the parser has got every
+    // `with_progress_interval` chars, and with the length of `input` once done. Offsets are
+    // into the input with its newlines normalized, so they can trail behind a little for
+    // files with `\r\n` line endings. Each parse starts afresh, so the parser can be reused.
+    pub fn parse_with_progress<F: FnMut(usize)>(&mut self, input : &str, progress : F) -> Result<CssRoot, CssError> {
+        let length = input.len();
+        // Editors on Windows like to start UTF-8 files with a byte order mark.
+        let input = normalize_newlines(input.strip_prefix('\u{FEFF}').unwrap_or(input));
+        let mut builder = TreeBuilder::new(&input, self.max_depth, self.source.as_deref());
+        let root = builder.build(length, self.progress_interval, progress);
+        self.position = builder.position();
+        return root;
+    }
 }
 
-struct CssParser {
+// The state of a single parse, made afresh for each one.
+struct TreeBuilder<'a> {
+    input : &'a str,
     stack : Vec<CssContext>,
-    char_buffer : Vec<char>,
+    stack_positions : Vec<(usize, usize)>,
+    char_buffer : Buffer<'a>,
+    // How much of the buffer ended inside a context that does not trim, see `trims_buffer`.
+    untrimmed_len : usize,
+    // The last key as written, kept for when it turns out to start a selector like `a :hover`.
+    key_source : Buffer<'a>,
+    // Whether the input opens with `@charset `, the only place one counts.
+    starts_with_charset : bool,
     current_char: char,
+    // The byte offset of `current_char` in the input.
+    current_offset: usize,
+    // Set when a string or comment runs into the end of the input, or on nesting past
+    // `max_depth`, which stops the parse.
+    error: Option<CssError>,
+    max_depth: usize,
+    // The name rule sets record as their source, and the line the last selector or key began on.
+    source: Option<&'a str>,
+    selector_line: usize,
+    line: usize,
+    column: usize,
 
     root: CssRoot,
-    selector: CssSelector,
+    media: CssMediaQuery,
+    keyframes: CssKeyframes,
     ruleset: CssRuleSet,
+    parent_rule_sets: Vec<CssRuleSet>,
     rule: CssRule,
-    key: CssKey,
-    value: CssValue,
-    string: CssString,
-    none: CssNone
 }
-impl CssParser {
-
-    fn new() -> CssParser {
-        let mut parser = CssParser {
+impl<'a> TreeBuilder<'a> {
+    fn new(input : &'a str, max_depth : usize, source : Option<&'a str>) -> TreeBuilder<'a> {
+        let mut builder = TreeBuilder {
+            input,
             stack: Vec::new(),
-            char_buffer: Vec::new(),
+            stack_positions: Vec::new(),
+            char_buffer: Buffer::new(input),
+            untrimmed_len: 0,
+            key_source: Buffer::new(input),
+            starts_with_charset: input.starts_with("@charset "),
             current_char: '\0',
+            current_offset: 0,
+            error: None,
+            max_depth,
+            source,
+            selector_line: 1,
+            line: 1,
+            column: 0,
 
             root: CssRoot::new(),
-            selector: CssSelector::new(),
+            media: CssMediaQuery::new(),
+            keyframes: CssKeyframes::new(),
             ruleset: CssRuleSet::new(),
-            rule: CssRule::new(),
-            key: CssKey::new(),
-            value: CssValue::new(),
-            string: CssString::new(),
-            none: CssNone::new()
+            parent_rule_sets: Vec::new(),
+            rule: CssRule::default(),
         };
-        parser.push_context(CssContext::Root);
+        builder.push_context(CssContext::Root);
+
+        return builder;
+    }
 
-        return parser;
+    fn position(&self) -> (usize, usize) {
+        return (self.line, self.column);
     }
 
     fn push_context(&mut self, context : CssContext) {
+        if self.stack.len() >= self.max_depth {
+            let (line, column) = self.position();
+            self.error.get_or_insert(CssError::DepthExceeded {line, column});
+            return;
+        }
+        // A nested rule set starts out looking like a declaration.
+        if context == CssContext::Selector || context == CssContext::Key {
+            self.selector_line = self.line;
+        }
         self.stack.push(context);
+        self.stack_positions.push(self.position());
+    }
+
+    fn rule_set_source(&self) -> Option<(String, usize)> {
+        return self.source.map(|name| (name.to_string(), self.selector_line));
     }
 
     fn pop_context(&mut self) {
         let _ = self.stack.pop();
+        let _ = self.stack_positions.pop();
+    }
+
+    // Where the innermost still open `{` block began.
+    fn block_position(&self) -> (usize, usize) {
+        return match self.stack.iter().rposition(|x| x.is_block()) {
+            Some(i) => self.stack_positions[i],
+            None => self.position()
+        }
     }
 
     fn flush_char_buffer(&mut self) -> String {
-        let val: String = self.char_buffer.iter().cloned().collect();
+        let kept = mem::take(&mut self.untrimmed_len);
+        let val = trim_buffered(self.char_buffer.as_str(), kept);
         self.char_buffer.clear();
-        return val.trim().to_string();
+        return val;
     }
 
-    fn push_char(&mut self, char: char) {
-        self.char_buffer.push(char);
+    // In bytes, as `untrimmed_len` is.
+    fn buffered_len(&self) -> usize {
+        return self.char_buffer.len();
     }
 
-    fn get_css_for_context(&self, context : CssContext) -> &Css {
-        return match context {
-            CssContext::Root => &self.root,
-            CssContext::Selector => &self.selector,
-            CssContext::RuleSet => &self.ruleset,
-            CssContext::Key => &self.key,
-            CssContext::Value => &self.value,
-            CssContext::String => &self.string,
-            CssContext::None => &self.none
+    fn push_char(&mut self) {
+        let start = self.current_offset;
+        self.char_buffer.push_range(start, start + self.current_char.len_utf8());
+    }
+
+    // `ident` is ASCII, so a match always starts on a char boundary.
+    fn buffer_ends_with_ident(&self, ident : &str) -> bool {
+        let buffer = self.char_buffer.as_str().as_bytes();
+        if buffer.len() < ident.len() {
+            return false;
         }
+
+        let start = buffer.len() - ident.len();
+        let matches = buffer[start..].eq_ignore_ascii_case(ident.as_bytes());
+        let bounded = matches && match self.char_buffer.as_str()[..start].chars().next_back() {
+            Some(before) => !(before.is_alphanumeric() || before == '-' || before == '_'),
+            None => true
+        };
+        return bounded;
     }
 
-    fn parse_char(&mut self) {
-        let char = self.current_char;
-        let current_context = match self.stack.last() {
+    fn current_context(&self) -> CssContext {
+        return match self.stack.last() {
             Some(x) => *x,
             None => CssContext::None
-        };
+        }
+    }
 
-        let css = self.get_css_for_context(current_context);
-        let test_result = css.test(char);
-        let command = test_result.command;
-        let next_context = test_result.context;
+    fn parse_char(&mut self) {
+        let context = self.current_context();
+        let char = self.current_char;
+        let test_result = self.test(context);
 
-        match command {
+        match test_result.command {
             CssCommand::End => {
                 self.pop_context();
-                css.end();
+                self.end(context);
             },
             CssCommand::EndIncludeChar => {
                 self.pop_context();
-                css.end();
-                self.push_char(char);
+                self.end(context);
+                self.push_char();
+                if !context.trims_buffer() {
+                    self.untrimmed_len = self.buffered_len();
+                }
             },
             CssCommand::EndKeepChar => {
                 self.pop_context();
-                css.end();
+                self.end(context);
                 self.parse_char();
             },
             CssCommand::Append => {
-                css.append();
+                self.append(context);
             },
             CssCommand::Begin => {
-                self.push_context(next_context);
-                css.begin();
+                self.push_context(test_result.context);
+                self.begin(context);
+                // A selector or key can start with an escape too, as in `\31 0 {`.
+                if char == '\\' && matches!(test_result.context, CssContext::Selector | CssContext::Key) {
+                    self.push_context(CssContext::Escape);
+                }
+            },
+            CssCommand::Discard => {},
+            CssCommand::Reject => {
+                let (line, column) = self.position();
+                self.error.get_or_insert(CssError::UnexpectedToken {token: char, line, column});
             },
             CssCommand::None => {
-                self.push_char(char)
+                self.push_char()
+            }
+        }
+    }
+
+    fn test(&self, context : CssContext) -> CssTestResult {
+        let char = self.current_char;
+
+        return match context {
+            CssContext::Root => match char {
+                ' '  |
+                '\n' |
+                '\r' |
+                '\t' => CssTestResult {context: CssContext::Root,     command: CssCommand::Discard},
+                '}' =>  CssTestResult {context: CssContext::Root,     command: CssCommand::Reject},
+                '@' =>  CssTestResult {context: CssContext::AtRule,   command: CssCommand::Begin},
+                _ =>    CssTestResult {context: CssContext::Selector, command: CssCommand::Begin},
+            },
+            CssContext::AtRule => match char {
+                '\\' => CssTestResult {context: CssContext::Escape, command: CssCommand::Begin},
+                '{' |
+                ';' => CssTestResult {context: CssContext::None,   command: CssCommand::End},
+                _ =>   CssTestResult {context: CssContext::AtRule, command: CssCommand::None},
+            },
+            // Each keyframe block is parsed as a rule set whose selectors are the frame stops.
+            CssContext::Media |
+            CssContext::Keyframes => match char {
+                ' '  |
+                '\n' |
+                '\r' |
+                '\t' => CssTestResult {context,                       command: CssCommand::Discard},
+                '}' =>  CssTestResult {context: CssContext::None,     command: CssCommand::End},
+                '@' =>  CssTestResult {context: CssContext::AtRule,   command: CssCommand::Begin},
+                _ =>    CssTestResult {context: CssContext::Selector, command: CssCommand::Begin},
+            },
+            // The block of an at-rule the parser does not understand, skipped up to its matching brace.
+            CssContext::IgnoredBlock => match char {
+                '{' => CssTestResult {context: CssContext::IgnoredBlock, command: CssCommand::Begin},
+                '}' => CssTestResult {context: CssContext::None,         command: CssCommand::End},
+                _ =>   CssTestResult {context: CssContext::IgnoredBlock, command: CssCommand::Discard},
+            },
+            CssContext::Selector => match char {
+                '\\' => CssTestResult {context: CssContext::Escape,            command: CssCommand::Begin},
+                '{' => CssTestResult {context: CssContext::None,              command: CssCommand::End},
+                '}' => CssTestResult {context: CssContext::Selector,          command: CssCommand::Reject},
+                ',' => CssTestResult {context: CssContext::Selector,          command: CssCommand::Append},
+                '[' => CssTestResult {context: CssContext::AttributeSelector, command: CssCommand::Begin},
+                '(' => CssTestResult {context: CssContext::SelectorArguments, command: CssCommand::Begin},
+                _ =>   CssTestResult {context: CssContext::Selector,          command: CssCommand::None},
+            },
+            // Commas and braces inside `:not(a, b)` or `:nth-child(2n+1)` are all part of the selector.
+            CssContext::SelectorArguments => match char {
+                '\\' => CssTestResult {context: CssContext::Escape,            command: CssCommand::Begin},
+                '(' =>  CssTestResult {context: CssContext::SelectorArguments, command: CssCommand::Begin},
+                ')' =>  CssTestResult {context: CssContext::None,              command: CssCommand::EndIncludeChar},
+                _ =>    CssTestResult {context: CssContext::SelectorArguments, command: CssCommand::None},
+            },
+            // Operators, commas and braces inside `[href^="a,{"]` are all part of the selector.
+            CssContext::AttributeSelector => match char {
+                '\\' => CssTestResult {context: CssContext::Escape,            command: CssCommand::Begin},
+                ']'  => CssTestResult {context: CssContext::None,              command: CssCommand::EndIncludeChar},
+                _ =>    CssTestResult {context: CssContext::AttributeSelector, command: CssCommand::None},
+            },
+            // Font face descriptors are parsed by the regular key/value contexts into the pending rule set.
+            CssContext::RuleSet |
+            CssContext::FontFace => match char {
+                ' '  |
+                '\n' |
+                '\r' |
+                '\t' => CssTestResult {context,                      command: CssCommand::None},
+                ';' =>  CssTestResult {context,                      command: CssCommand::Discard},
+                '}' =>  CssTestResult {context: CssContext::None,    command: CssCommand::End},
+                _ =>    CssTestResult {context: CssContext::Key,     command: CssCommand::Begin},
+            },
+            // A `{` after what looked like a key or value opens a nested rule set instead.
+            // A key without a value, as in `a { color }`, is dropped.
+            CssContext::Key => match char {
+                '\\' => CssTestResult {context: CssContext::Escape, command: CssCommand::Begin},
+                ':' |
+                ';' |
+                '{' => CssTestResult {context: CssContext::None, command: CssCommand::End},
+                '}' => CssTestResult {context: CssContext::None, command: CssCommand::EndKeepChar},
+                _ =>   CssTestResult {context: CssContext::Key,  command: CssCommand::None},
+            },
+            CssContext::Value => match char {
+                '(' if self.buffer_ends_with_ident("url") =>
+                       CssTestResult {context: CssContext::Url,    command: CssCommand::Begin},
+                '\\' => CssTestResult {context: CssContext::Escape, command: CssCommand::Begin},
+                ';' |
+                '{' => CssTestResult {context: CssContext::None,   command: CssCommand::End},
+                '}' => CssTestResult {context: CssContext::None,   command: CssCommand::EndKeepChar},
+                _ =>   CssTestResult {context: CssContext::Value,  command: CssCommand::None},
+            },
+            // Everything up to the closing paren of `url(` is captured verbatim, so `;` and `:`
+            // inside an unquoted url do not end the value.
+            CssContext::Url => match char {
+                '\\' => CssTestResult {context: CssContext::Escape, command: CssCommand::Begin},
+                ')'  => CssTestResult {context: CssContext::None,   command: CssCommand::EndIncludeChar},
+                _ =>    CssTestResult {context: CssContext::Url,    command: CssCommand::None},
+            },
+            // The character after a backslash is kept verbatim and never ends the value;
+            // decoding is left to `unescape`.
+            CssContext::Escape => CssTestResult {context: CssContext::None, command: CssCommand::EndIncludeChar},
+            CssContext::None => CssTestResult {context: CssContext::None, command: CssCommand::None}
+        }
+    }
+
+    // Runs in the context that is being left for the pushed one.
+    fn begin(&mut self, context : CssContext) {
+        match context {
+            CssContext::Root |
+            CssContext::AtRule |
+            CssContext::Media |
+            CssContext::FontFace |
+            CssContext::Keyframes |
+            CssContext::RuleSet |
+            CssContext::Selector |
+            CssContext::AttributeSelector |
+            CssContext::SelectorArguments |
+            CssContext::Key |
+            CssContext::Value |
+            CssContext::Url => self.push_char(),
+            _ => {}
+        }
+    }
+
+    fn append(&mut self, context : CssContext) {
+        if context == CssContext::Selector {
+            self.push_selector();
+        }
+    }
+
+    // Runs after `context` has been popped, so `current_context` is its parent.
+    fn end(&mut self, context : CssContext) {
+        match context {
+            CssContext::AtRule => self.end_at_rule(),
+            CssContext::Media => {
+                let current_media = mem::replace(&mut self.media, CssMediaQuery::new());
+                self.root.media_queries.push(current_media);
+            },
+            CssContext::FontFace => {
+                let mut font_face = CssFontFace::new();
+                // Nested blocks are not allowed in `@font-face`, and are dropped with it.
+                font_face.descriptors = mem::take(&mut self.ruleset).rules;
+                self.root.font_faces.push(font_face);
+            },
+            CssContext::Keyframes => {
+                let current_keyframes = mem::replace(&mut self.keyframes, CssKeyframes::new());
+                self.root.keyframes.push(current_keyframes);
+            },
+            CssContext::Selector => {
+                self.push_selector();
+                self.ruleset.source = self.rule_set_source();
+                self.push_context(CssContext::RuleSet);
+            },
+            CssContext::RuleSet => self.end_rule_set(),
+            CssContext::Key if self.current_char == '{' => {
+                let selectors = self.flush_char_buffer();
+                self.begin_nested_rule_set(&selectors);
+            },
+            CssContext::Key if self.current_char == ';' || self.current_char == '}' => {
+                self.flush_char_buffer();
+            },
+            CssContext::Key => {
+                let kept = mem::take(&mut self.untrimmed_len);
+                self.key_source = mem::replace(&mut self.char_buffer, Buffer::new(self.input));
+                self.rule.key = trim_buffered(self.key_source.as_str(), kept);
+                self.push_context(CssContext::Value);
+            },
+            // The `:` of a selector like `&:hover` was taken to end a key. Neither side is
+            // trimmed yet, so whitespace around the `:` is kept.
+            CssContext::Value if self.current_char == '{' => {
+                self.untrimmed_len = 0;
+                let value = mem::replace(&mut self.char_buffer, Buffer::new(self.input));
+                let selectors = format!("{}:{}", self.key_source.as_str(), value.as_str());
+                self.rule = CssRule::default();
+                self.begin_nested_rule_set(&selectors);
+            },
+            CssContext::Value => self.end_value(),
+            _ => {}
+        }
+    }
+
+    fn end_at_rule(&mut self) {
+        let prelude = self.flush_char_buffer();
+        let (name, params) = split_at_rule(&prelude);
+        let has_block = self.current_char == '{';
+        let nested = self.stack.contains(&CssContext::Media);
+        let starts_stylesheet = self.starts_with_charset && self.root == CssRoot::new();
+        let has_blocks = !(self.root.rule_sets.is_empty() && self.root.media_queries.is_empty()
+            && self.root.font_faces.is_empty() && self.root.keyframes.is_empty());
+
+        // Prefixed at-rules like `@-webkit-keyframes` are read as the standard ones.
+        match unprefixed(&name.to_ascii_lowercase()) {
+            "media" if has_block && !nested => {
+                self.media.condition = params.to_string();
+                self.media.queries = parse_media_queries(params);
+                self.push_context(CssContext::Media);
+            },
+            "font-face" if has_block => self.push_context(CssContext::FontFace),
+            "keyframes" if has_block => {
+                self.keyframes.name = params.to_string();
+                self.push_context(CssContext::Keyframes);
+            },
+            // Only valid as the very first thing in the stylesheet; a later one is ignored.
+            "charset" if !has_block && starts_stylesheet => {
+                self.root.charset = url_argument(params).map(|charset| charset.to_string());
+            },
+            "namespace" if !has_block && !has_blocks => {
+                if let Some(namespace) = namespace(params) {
+                    self.root.namespaces.push(namespace);
+                }
+            },
+            "import" if !has_block => {
+                if let Some(path) = url_argument(params) {
+                    self.root.imports.push(path.to_string());
+                }
+            },
+            _ if has_block => self.push_context(CssContext::IgnoredBlock),
+            _ => {}
+        }
+    }
+
+    // Stray commas as in `h1,, h2` would otherwise leave empty selectors behind.
+    fn push_selector(&mut self) {
+        let chars = self.flush_char_buffer();
+        if !chars.is_empty() {
+            self.ruleset.selectors.push(chars);
+        }
+    }
+
+    // A block without any selectors in front, as in `a { { color: red } }`, matches nothing
+    // and is skipped.
+    fn begin_nested_rule_set(&mut self, selectors : &str) {
+        let mut child = CssRuleSet::new();
+        child.selectors = split_selectors(selectors);
+        child.source = self.rule_set_source();
+        if child.selectors.is_empty() {
+            self.push_context(CssContext::IgnoredBlock);
+            return;
+        }
+
+        let parent = mem::replace(&mut self.ruleset, child);
+        self.parent_rule_sets.push(parent);
+        self.push_context(CssContext::RuleSet);
+    }
+
+    fn end_rule_set(&mut self) {
+        if let Some(parent) = self.parent_rule_sets.pop() {
+            let child = mem::replace(&mut self.ruleset, parent);
+            self.ruleset.children.push(child);
+            return;
+        }
+
+        let current_rule_set = mem::replace(&mut self.ruleset, CssRuleSet::new());
+        match self.current_context() {
+            CssContext::Media => self.media.rule_sets.push(current_rule_set),
+            CssContext::Keyframes => {
+                let stop = current_rule_set.selectors.join(", ");
+                self.keyframes.frames.push((stop, current_rule_set.rules));
+            },
+            _ => self.root.rule_sets.push(current_rule_set)
+        }
+    }
+
+    fn end_value(&mut self) {
+        let value = self.flush_char_buffer();
+        let (value, mut flags) = strip_flags(&value);
+        self.rule.set_value(value);
+        self.rule.important = flags.iter().any(|flag| flag == "important");
+        flags.retain(|flag| flag != "important");
+        self.rule.flags = flags;
+
+        // A declaration like `: red` has nothing to set.
+        let current_rule = mem::take(&mut self.rule);
+        if !current_rule.key.is_empty() {
+            self.ruleset.rules.push(current_rule);
+        }
+    }
+
+    // Parses the whole input, reporting `length` as the end to `progress`.
+    fn build<F: FnMut(usize)>(&mut self, length : usize, progress_interval : usize, mut progress : F)
+        -> Result<CssRoot, CssError> {
+        let input = self.input;
+        let mut unreported = 0;
+        for spanned in spanned_tokens(input) {
+            if self.error.is_some() {
+                break;
+            }
+            let text = &input[spanned.start..spanned.end];
+
+            // Everything before this token has been parsed.
+            if unreported >= progress_interval {
+                unreported = 0;
+                progress(spanned.start);
+            }
+            unreported += text.chars().count();
+            let context = self.current_context();
+
+            match spanned.kind {
+                TokenKind::Comment => {
+                    if context == CssContext::Selector {
+                        self.separate_selector();
+                    }
+                    self.advance_over(text);
+                    if spanned.is_unterminated(text) {
+                        let (line, column) = self.position();
+                        self.error = Some(CssError::UnexpectedEof {line, column});
+                    }
+                },
+                // Strings and unquoted urls stay whole wherever they turn up, so a `}` inside
+                // one never closes a block. Outside the contexts that take them, the first char
+                // is fed as usual in case it starts a selector or key.
+                TokenKind::String |
+                TokenKind::Url => {
+                    if spanned.is_unterminated(text) {
+                        let (line, column) = (self.line, self.column + 1);
+                        self.error = Some(match spanned.kind {
+                            TokenKind::String => CssError::UnterminatedString {line, column},
+                            _ => CssError::UnexpectedEof {line, column}
+                        });
+                    }
+
+                    let mut rest = spanned.start;
+                    if !context.takes_strings() {
+                        let first = text.chars().next().unwrap();
+                        self.feed(first, rest);
+                        rest += first.len_utf8();
+                    }
+                    if self.current_context() != CssContext::IgnoredBlock {
+                        self.char_buffer.push_range(rest, spanned.end);
+                        self.untrimmed_len = self.buffered_len();
+                    }
+                    let rest = &input[rest..spanned.end];
+                    self.advance_over(rest);
+                },
+                TokenKind::Delim(char) => self.feed(char, spanned.start),
+                // The `(` opens arguments or a url as it would after any other name.
+                TokenKind::Function => {
+                    let paren = spanned.end - 1;
+                    self.feed_run(spanned.start, paren);
+                    if self.error.is_none() {
+                        self.feed('(', paren);
+                    }
+                },
+                _ => self.feed_run(spanned.start, spanned.end)
             }
         }
+
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        self.check_eof()?;
+        progress(length);
+        return Ok(mem::take(&mut self.root));
+    }
+
+    // A comment between two compounds, as in `a/**/b`, keeps them apart instead of
+    // gluing them into one name. Trailing whitespace is trimmed before `{` as usual.
+    fn separate_selector(&mut self) {
+        if self.char_buffer.as_str().chars().next_back().is_some_and(|c| !c.is_whitespace()) {
+            self.char_buffer.push_str(" ");
+        }
+    }
+
+    // Takes a run of whitespace or a word, like a name or number, whole where the context
+    // treats all of its chars alike. Until it does, chars are fed one at a time, as when
+    // the first char of a word opens a selector or key.
+    fn feed_run(&mut self, start : usize, end : usize) {
+        let mut pos = start;
+        while pos < end && self.error.is_none() {
+            let run = &self.input[pos..end];
+            match self.current_context().run_command(run) {
+                CssCommand::None => {
+                    self.char_buffer.push_range(pos, end);
+                    // As after an escape fed on its own, the escaped char survives the trim.
+                    if let Some(escaped) = escaped_end(run) {
+                        self.untrimmed_len = self.buffered_len() - (run.len() - escaped);
+                    }
+                    self.advance_over(run);
+                    return;
+                },
+                CssCommand::Discard => {
+                    self.advance_over(run);
+                    return;
+                },
+                _ => {
+                    let char = run.chars().next().unwrap();
+                    self.feed(char, pos);
+                    pos += char.len_utf8();
+                }
+            }
+        }
+    }
+
+    fn feed(&mut self, char : char, offset : usize) {
+        self.column += 1;
+        self.current_char = char;
+        self.current_offset = offset;
+        self.parse_char();
+        self.end_char(char);
+    }
+
+    fn advance_over(&mut self, text : &str) {
+        for char in text.chars() {
+            self.column += 1;
+            self.end_char(char);
+        }
     }
 
-    fn parse(&mut self, css : String) {
-        for char in css.chars() {
-            self.current_char = char;
-            self.parse_char();
+    fn end_char(&mut self, char : char) {
+        if char == '\n' {
+            self.line += 1;
+            self.column = 0;
         }
+    }
+
+    fn check_eof(&self) -> Result<(), CssError> {
+        let (line, column) = self.position();
 
-        println!("{:?}", self.root);
+        return match self.current_context() {
+            CssContext::Root => Ok(()),
+            CssContext::Selector if self.char_buffer.as_str().trim().is_empty() => Ok(()),
+            CssContext::RuleSet |
+            CssContext::Key |
+            CssContext::Value |
+            CssContext::Media |
+            CssContext::FontFace |
+            CssContext::Keyframes |
+            CssContext::IgnoredBlock => {
+                let (line, column) = self.block_position();
+                Err(CssError::UnterminatedBlock {line, column})
+            },
+            CssContext::AtRule |
+            CssContext::AttributeSelector |
+            CssContext::SelectorArguments |
+            CssContext::Url |
+            CssContext::Escape |
+            CssContext::Selector |
+            CssContext::None => Err(CssError::UnexpectedEof {line, column})
+        }
+    }
+}
+
+// The text buffered for a selector, key, value or at-rule prelude. While it is one unbroken
+// run of the input it is only a range of it, and it is copied out once something that isn't
+// next in the input is added, like the space standing in for a comment.
+#[derive(Debug)]
+struct Buffer<'a> {
+    input: &'a str,
+    start: usize,
+    end: usize,
+    owned: Option<String>,
+}
+impl<'a> Buffer<'a> {
+    fn new(input : &'a str) -> Buffer<'a> {
+        return Buffer {input, start: 0, end: 0, owned: None};
+    }
+
+    fn push_range(&mut self, start : usize, end : usize) {
+        if self.owned.is_none() && self.start == self.end {
+            self.start = start;
+            self.end = end;
+        } else if self.owned.is_none() && self.end == start {
+            self.end = end;
+        } else {
+            let text = &self.input[start..end];
+            self.push_str(text);
+        }
+    }
+
+    fn push_str(&mut self, text : &str) {
+        let (input, start, end) = (self.input, self.start, self.end);
+        self.owned.get_or_insert_with(|| input[start..end].to_string()).push_str(text);
+    }
+
+    fn as_str(&self) -> &str {
+        return match self.owned {
+            Some(ref owned) => owned,
+            None => &self.input[self.start..self.end]
+        }
+    }
+
+    fn len(&self) -> usize {
+        return self.as_str().len();
+    }
+
+    fn clear(&mut self) {
+        *self = Buffer::new(self.input);
     }
 }
 
@@ -340,16 +1270,490 @@ enum CssCommand {
     End,
     EndIncludeChar,
     EndKeepChar,
+    Discard,
+    // The char can't appear here, like a `}` without a block to close, which fails the parse.
+    Reject,
     None,
 }
 
 #[derive(PartialEq, Eq, Copy, Clone, Debug)]
 enum CssContext {
     Root,
+    AtRule,
+    Media,
+    FontFace,
+    Keyframes,
+    IgnoredBlock,
     Selector,
+    AttributeSelector,
+    SelectorArguments,
     RuleSet,
     Key,
     Value,
-    String,
+    Escape,
+    Url,
     None,
 }
+impl CssContext {
+    // Whitespace closing a url or escape is content and must survive the flush, as must
+    // that of a string.
+    fn trims_buffer(&self) -> bool {
+        return !matches!(*self, CssContext::Url | CssContext::Escape);
+    }
+
+    // What every char of `run`, whitespace or a word, would do in this context, when that is
+    // to be buffered or skipped. Words only hold structural chars as escapes, which are kept
+    // verbatim anywhere they can appear. Anything else is given as `Begin`.
+    fn run_command(&self, run : &str) -> CssCommand {
+        let skipped = run.chars().all(|c| matches!(c, ' ' | '\n' | '\r' | '\t'));
+        return match *self {
+            CssContext::Root |
+            CssContext::Media |
+            CssContext::Keyframes if skipped => CssCommand::Discard,
+            CssContext::IgnoredBlock if !run.contains(['{', '}']) => CssCommand::Discard,
+            CssContext::RuleSet |
+            CssContext::FontFace if skipped => CssCommand::None,
+            CssContext::AtRule |
+            CssContext::Selector |
+            CssContext::AttributeSelector |
+            CssContext::SelectorArguments |
+            CssContext::Key |
+            CssContext::Value |
+            CssContext::Url => CssCommand::None,
+            _ => CssCommand::Begin
+        }
+    }
+
+    // Contexts where a quote opens a string whose contents have no meaning to the parser.
+    fn takes_strings(&self) -> bool {
+        return matches!(*self,
+            CssContext::AtRule |
+            CssContext::IgnoredBlock |
+            CssContext::AttributeSelector |
+            CssContext::SelectorArguments |
+            CssContext::Value |
+            CssContext::Url);
+    }
+
+    fn is_block(&self) -> bool {
+        return matches!(*self,
+            CssContext::RuleSet |
+            CssContext::Media |
+            CssContext::FontFace |
+            CssContext::Keyframes |
+            CssContext::IgnoredBlock);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(css : &str) -> CssRoot {
+        return ::parse_str(css).unwrap();
+    }
+
+    fn selectors(rule_set : &CssRuleSet) -> Vec<&str> {
+        return rule_set.selectors.iter().map(|selector| selector.as_str()).collect();
+    }
+
+    #[test]
+    fn parses_a_rule_set() {
+        let root = parse("a { color: red; }");
+        assert_eq!(root.rule_sets.len(), 1);
+        assert_eq!(selectors(&root.rule_sets[0]), vec!["a"]);
+        assert_eq!(root.rule_sets[0].rules.len(), 1);
+        assert_eq!(root.rule_sets[0].rules[0].key, "color");
+        assert_eq!(root.rule_sets[0].rules[0].value, "red");
+    }
+
+    #[test]
+    fn parse_returns_every_rule_set() {
+        let root = CssParser::new().parse("a { x: 1 } b { y: 2 } c { z: 3 }".to_string()).unwrap();
+        assert_eq!(root.rule_sets.len(), 3);
+    }
+
+    #[test]
+    fn string_and_str_input_agree() {
+        let css = "@media print { a { b: c } } d, e { f: \"g\" !important; h { i: url(j;k) } }";
+        let root = CssParser::new().parse(css.to_string()).unwrap();
+        assert_eq!(CssParser::new().parse_bytes(css).unwrap(), root);
+        assert_eq!(root.rule_sets[0].children[0].get("i"), Some("url(j;k)"));
+    }
+
+    #[test]
+    fn reports_unterminated_input() {
+        assert!(matches!(::parse_str("a { color: red"), Err(CssError::UnterminatedBlock {line: 1, column: 3})));
+        assert!(matches!(::parse_str("a { color: \"red"), Err(CssError::UnterminatedString {..})));
+        assert!(matches!(::parse_str("a "), Err(CssError::UnexpectedEof {..})));
+        assert!(matches!(::parse_file("/nonexistent.css"), Err(CssError::Io(_))));
+        assert!(::parse_str("a {}\n  ").is_ok());
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        let error = ::parse_str("a {}\nb { c: \"x\";\n   d: \"oops; }").unwrap_err();
+        assert_eq!(error.to_string(), "unterminated string at line 3, column 7");
+    }
+
+    #[test]
+    fn skips_comments() {
+        let root = parse("/* c */ a { color: red; }");
+        assert_eq!(selectors(&root.rule_sets[0]), vec!["a"]);
+        assert_eq!(root.rule_sets[0].rules[0].value, "red");
+
+        let root = parse("a { color: /* x */ red; /* y */ b: c }");
+        assert_eq!(root.rule_sets[0].rules[0].value, "red");
+        assert_eq!(root.rule_sets[0].rules[1].key, "b");
+
+        assert_eq!(parse("a { c: \"/* no */\"; }").rule_sets[0].rules[0].value, "\"/* no */\"");
+        assert!(::parse_str("a { /* x ").is_err());
+    }
+
+    #[test]
+    fn get_takes_the_last_declaration() {
+        let root = parse("a { color: red; margin: 0; color: blue }");
+        let rule_set = &root.rule_sets[0];
+        assert_eq!(rule_set.get("color"), Some("blue"));
+        assert_eq!(rule_set.get_all("color"), vec!["red", "blue"]);
+        assert_eq!(rule_set.get("width"), None);
+    }
+
+    #[test]
+    fn reads_important_flags() {
+        let root = parse("a { color: red !important; b: blue ! important ; c: \"x!important\"; d: e!IMPORTANT }");
+        let rules: Vec<_> = root.rule_sets[0].rules.iter().map(|rule| (rule.value.as_str(), rule.important)).collect();
+        assert_eq!(rules, vec![("red", true), ("blue", true), ("\"x!important\"", false), ("e", true)]);
+    }
+
+    #[test]
+    fn groups_selectors() {
+        for css in ["h1, h2, h3 { margin: 0; }", "  h1 ,\n\th2\r\n,   h3{margin:0}"].iter() {
+            let root = parse(css);
+            assert_eq!(root.rule_sets.len(), 1);
+            assert_eq!(selectors(&root.rule_sets[0]), vec!["h1", "h2", "h3"]);
+        }
+        assert_eq!(selectors(&parse("h1,,h2, {a:b}").rule_sets[0]), vec!["h1", "h2"]);
+    }
+
+    #[test]
+    fn parses_media_blocks() {
+        let root = parse("a { color: red; }\n@media (max-width: 600px) {\n  b { x: 1 }\n  c, d { y: 2; }\n}");
+        assert_eq!(root.rule_sets.len(), 1);
+        assert_eq!(root.media_queries.len(), 1);
+        let media = &root.media_queries[0];
+        assert_eq!(media.condition, "(max-width: 600px)");
+        assert_eq!(media.rule_sets.len(), 2);
+        assert_eq!(selectors(&media.rule_sets[1]), vec!["c", "d"]);
+    }
+
+    #[test]
+    fn parses_imports() {
+        let root = parse("@import \"theme.css\";\n@import url(base.css);\na { b: c }\n@import 'late.css' print;");
+        assert_eq!(root.imports, vec!["theme.css", "base.css", "late.css"]);
+        assert_eq!(root.rule_sets.len(), 1);
+    }
+
+    #[test]
+    fn parses_font_faces() {
+        let root = parse("@font-face { font-family: \"Foo\"; src: url(foo.woff); }\na { b: c }");
+        assert_eq!(root.font_faces.len(), 1);
+        assert_eq!(root.font_faces[0].get("font-family"), Some("\"Foo\""));
+        assert_eq!(root.font_faces[0].get("src"), Some("url(foo.woff)"));
+        assert_eq!(root.rule_sets.len(), 1);
+    }
+
+    #[test]
+    fn font_face_drops_nested_blocks() {
+        let root = ::parse_str("@font-face { font-family: x; a { b: c } } x { y: z }").unwrap();
+        assert_eq!(root.font_faces[0].get("font-family"), Some("x"));
+        assert_eq!(root.rule_sets.len(), 1);
+        assert_eq!(root.rule_sets[0].selectors, vec!["x".to_string()]);
+        assert!(root.rule_sets[0].children.is_empty());
+        assert_eq!(root.rule_sets[0].get("y"), Some("z"));
+    }
+
+    #[test]
+    fn parses_keyframes() {
+        let root = parse("@keyframes spin {\n from { transform: rotate(0deg); }\n to { transform: rotate(360deg) }\n 50% { opacity: 0.5; color: red }\n}");
+        let keyframes = &root.keyframes[0];
+        assert_eq!(keyframes.name, "spin");
+        let stops: Vec<_> = keyframes.frames.iter().map(|frame| frame.0.as_str()).collect();
+        assert_eq!(stops, vec!["from", "to", "50%"]);
+        assert_eq!(keyframes.frames[2].1.len(), 2);
+    }
+
+    #[test]
+    fn keeps_escapes_in_strings() {
+        let root = parse(r#"a { content: "a\"b"; b: "a\\b"; c: '\41 B' }"#);
+        let rules = &root.rule_sets[0].rules;
+        assert_eq!(rules[0].value, r#""a\"b""#);
+        assert_eq!(unescape(&rules[0].value), r#""a"b""#);
+        assert_eq!(unescape(&rules[1].value), r#""a\b""#);
+        assert_eq!(unescape(&rules[2].value), "'AB'");
+        assert!(::parse_str("a { b: \"x\\").is_err());
+    }
+
+    #[test]
+    fn reads_urls_verbatim() {
+        let root = parse("a { background: url(images/bg.png) no-repeat; b: url(\"x;y.png\"); c: url(data:image/png;base64,AA//x) }");
+        let rule_set = &root.rule_sets[0];
+        assert_eq!(rule_set.get("background"), Some("url(images/bg.png) no-repeat"));
+        assert_eq!(rule_set.get("b"), Some("url(\"x;y.png\")"));
+        assert_eq!(rule_set.get("c"), Some("url(data:image/png;base64,AA//x)"));
+    }
+
+    #[test]
+    fn iterates_every_rule() {
+        let root = parse("a { x: 1; y: 2; } b { z: 3; } @media print { c { w: 4; } }");
+        let rules: Vec<_> = root.rules().map(|(rule_set, rule)| (rule_set.selectors[0].as_str(), rule.key.as_str())).collect();
+        assert_eq!(rules.len(), 4);
+        assert_eq!(rules[3], ("c", "w"));
+    }
+
+    #[test]
+    fn reads_the_prologue() {
+        let root = parse("@charset \"utf-8\";\n@namespace svg url(\"http://www.w3.org/2000/svg\");\na { b: c; }");
+        assert_eq!(root.charset.as_deref(), Some("utf-8"));
+        assert_eq!(root.namespaces, vec![(Some("svg".to_string()), "http://www.w3.org/2000/svg".to_string())]);
+
+        let late = parse("a { b: c; }\n@charset \"utf-8\";\n@namespace x url(y);");
+        assert_eq!(late.charset, None);
+        assert!(late.namespaces.is_empty());
+    }
+
+    #[test]
+    fn flattens_nested_rule_sets() {
+        let root = parse(".card, .box { color: red; & .title { font-weight: bold; &:hover { color: blue; } } }");
+        let card = &root.rule_sets[0];
+        assert_eq!(card.children.len(), 1);
+        assert_eq!(selectors(&card.children[0]), vec!["& .title"]);
+
+        let flat: Vec<_> = card.flatten().iter().map(|rule_set| rule_set.selectors.join(", ")).collect();
+        assert_eq!(flat, vec![".card, .box", ".card .title, .box .title", ".card .title:hover, .box .title:hover"]);
+    }
+
+    #[test]
+    fn reports_duplicate_properties() {
+        let root = parse("a { color: red; margin: 0; color: blue; background: red; background: linear-gradient(red, blue) }");
+        assert_eq!(root.rule_sets[0].duplicate_properties(), vec!["color".to_string()]);
+    }
+
+    #[test]
+    fn keeps_whitespace_inside_strings() {
+        let root = parse("a { content: \"  spaced  \" ; b: c\\ ; }");
+        assert_eq!(root.rule_sets[0].rules[0].value, "\"  spaced  \"");
+        assert_eq!(root.rule_sets[0].rules[1].value, "c\\ ");
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serializes_to_json_and_back() {
+        let root = parse("a { width: 5px; color: red; display: block !important; } @media print { b { c: d; } } \
+            @keyframes k { from { x: 1; } }");
+        let json = ::serde_json::to_string(&root).unwrap();
+        assert!(json.contains("\"length\":{\"px\":5.0}"));
+        assert_eq!(::serde_json::from_str::<CssRoot>(&json).unwrap(), root);
+    }
+
+    #[test]
+    fn selects_rule_sets_by_selector() {
+        let root = parse(".button { a: b; } .x, .button { c: d; } .buttons { e: f; }");
+        let found = root.select(".button");
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[1].rules[0].key, "c");
+        assert!(root.select(".none").is_empty());
+    }
+
+    #[test]
+    fn crlf_parses_like_lf() {
+        let unix = "h1,\nh2\n{\n  color: red;\n  content: \"a\\\nb\";\n}\n@media print {\n  a { b: c }\n}\n";
+        let windows = unix.replace('\n', "\r\n");
+        assert_eq!(parse(&windows), parse(unix));
+        assert_eq!(selectors(&parse(&windows).rule_sets[0]), vec!["h1", "h2"]);
+    }
+
+    #[test]
+    fn empty_rule_sets_and_stylesheets() {
+        for css in ["a {}", "a { }", "a {\n}", "a{;}"].iter() {
+            let root = parse(css);
+            assert_eq!(root.rule_sets.len(), 1, "{}", css);
+            assert!(root.rule_sets[0].rules.is_empty(), "{}", css);
+        }
+        for css in ["", "   ", "\n\n", "/* c */"].iter() {
+            assert_eq!(parse(css), CssRoot::new(), "{:?}", css);
+        }
+        assert_eq!(parse("@media screen { a {} }").media_queries[0].rule_sets.len(), 1);
+    }
+
+    #[test]
+    fn comments_between_selector_and_brace() {
+        for css in ["a /* x */ { color: red; }", "a/* x */{ color: red; }", "a\n\n  {color:red}", "a /* { */ { color: red }"].iter() {
+            let root = parse(css);
+            assert_eq!(selectors(&root.rule_sets[0]), vec!["a"], "{}", css);
+            assert_eq!(root.rule_sets[0].rules[0].value, "red");
+        }
+        assert_eq!(selectors(&parse("a /* x */ , b /* y */ { color: red }").rule_sets[0]), vec!["a", "b"]);
+        assert_eq!(selectors(&parse("a/**/b { color: red }").rule_sets[0]), vec!["a b"]);
+    }
+
+    #[test]
+    fn rejects_deeply_nested_input() {
+        let deep = "a{".repeat(100_000);
+        assert!(matches!(::parse_str(&deep), Err(CssError::DepthExceeded {line: 1, ..})));
+
+        let ignored = format!("@foo {}", "{".repeat(100_000));
+        assert!(matches!(::parse_str(&ignored), Err(CssError::DepthExceeded {..})));
+
+        let closed = format!("{}{}", "a{".repeat(50), "}".repeat(50));
+        assert!(::parse_str(&closed).is_ok());
+        assert!(matches!(CssParser::new().with_max_depth(4).parse_bytes(&closed), Err(CssError::DepthExceeded {..})));
+    }
+
+    #[test]
+    fn reads_value_flags() {
+        let root = parse("a { $x: red !default; --y: 1px ! IMPORTANT  !global; c: \"hi!\"; d: a\\!b; f: x !1 }");
+        let rules = &root.rule_sets[0].rules;
+        assert_eq!((rules[0].value.as_str(), rules[0].important), ("red", false));
+        assert_eq!(rules[0].flags, vec!["default".to_string()]);
+        assert_eq!((rules[1].value.as_str(), rules[1].important), ("1px", true));
+        assert_eq!(rules[1].flags, vec!["global".to_string()]);
+        assert!(rules[2].flags.is_empty());
+        assert_eq!((rules[3].value.as_str(), rules[3].important), ("a\\!b", false));
+        assert_eq!(rules[4].value, "x !1");
+    }
+
+    #[test]
+    fn keeps_multibyte_text() {
+        let root = parse(".ünï, .😀 { content: \"ünïcödé 😀\"; font-family: Ærial }");
+        assert_eq!(selectors(&root.rule_sets[0]), vec![".ünï", ".😀"]);
+        assert_eq!(root.rule_sets[0].get("content"), Some("\"ünïcödé 😀\""));
+        assert_eq!(root.rule_sets[0].get("font-family"), Some("Ærial"));
+    }
+
+    #[test]
+    fn reports_increasing_progress() {
+        let css = "a { color: red; }\r\n".repeat(1000);
+        let mut offsets = Vec::new();
+        let root = CssParser::new().with_progress_interval(100)
+            .parse_with_progress(&css, |offset| offsets.push(offset))
+            .unwrap();
+        assert_eq!(root.rule_sets.len(), 1000);
+        assert!(offsets.len() > 100);
+        assert!(offsets.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(offsets.last(), Some(&css.len()));
+    }
+
+    #[test]
+    fn builds_rules() {
+        let rule = CssRule::new("color", "red").important();
+        assert_eq!(rule.to_string(), "color: red !important");
+        assert!(matches!(rule.parsed_value(), value::CssValue::Color(_)));
+        assert_eq!(CssRule::new("$x", "1px").flag("DEFAULT").to_string(), "$x: 1px !default");
+
+        let mut rule_set = CssRuleSet::new();
+        rule_set.selectors.push("a".to_string());
+        rule_set.rules.push(rule);
+        assert_eq!(parse("a { color: red !important }").rule_sets[0], rule_set);
+    }
+
+    #[test]
+    fn skips_a_leading_bom() {
+        assert_eq!(selectors(&parse("\u{FEFF}a { color: red }").rule_sets[0]), vec!["a"]);
+        assert_eq!(parse("\u{FEFF}@charset \"utf-8\";\r\na { color: red }").charset.as_deref(), Some("utf-8"));
+        assert_eq!(parse("\u{FEFF}/* only */"), CssRoot::new());
+        assert_eq!(parse("\u{FEFF}"), CssRoot::new());
+        // Anywhere else it is part of the content.
+        assert_eq!(selectors(&parse("a {}\u{FEFF}b {}").rule_sets[1]), vec!["\u{FEFF}b"]);
+    }
+
+    #[test]
+    fn matches_simple_selectors() {
+        let root = parse("h1 ,  h2, ul \n   li > a { color: red }");
+        let rule_set = &root.rule_sets[0];
+        assert!(rule_set.matches_simple("h2"));
+        assert!(rule_set.matches_simple("  h1 "));
+        assert!(rule_set.matches_simple("ul li\t> a"));
+        assert!(!rule_set.matches_simple("ul li>a"));
+        assert!(!rule_set.matches_simple("h1, h2"));
+    }
+
+    #[test]
+    fn reports_stray_closing_braces() {
+        assert!(matches!(::parse_str("a { color: red }\n}"), Err(CssError::UnexpectedToken {token: '}', line: 2, column: 1})));
+        assert!(matches!(::parse_str("a } b { c: d }"), Err(CssError::UnexpectedToken {column: 3, ..})));
+        assert!(matches!(::parse_str("@media x { a } "), Err(CssError::UnexpectedToken {..})));
+        assert!(::parse_str("d { e: \"}\" } [x=\"}\"] {} @media x { a { b: c } } .q { & r { s: t } }").is_ok());
+    }
+
+    #[test]
+    fn reads_vendor_prefixes() {
+        let root = parse("a { -webkit-transition: opacity 1s; -webkit-transform: rotate(45deg) }\n\
+            @-moz-keyframes spin { from { top: 0 } to { top: 10px } }");
+        assert_eq!(root.rule_sets[0].rules[0].key, "-webkit-transition");
+        assert!(matches!(root.rule_sets[0].rules[1].parsed_value(), value::CssValue::Transform(_)));
+        assert_eq!(root.keyframes[0].name, "spin");
+        assert!(::validate(&root).is_empty());
+
+        assert_eq!(unprefixed("-MOZ-keyframes"), "keyframes");
+        assert_eq!(unprefixed("--var"), "--var");
+        assert_eq!(unprefixed("-moz-"), "-moz-");
+    }
+
+    #[test]
+    fn merges_stylesheets_in_order() {
+        let mut base = parse("@charset \"a\";\n:root { --c: red } a { color: var(--c) } b { top: 1px }");
+        base.merge(parse("@charset \"b\";\n:root { --c: blue } a { left: 2px }"));
+        assert_eq!(base.rule_sets.len(), 5);
+        assert_eq!(base.charset.as_deref(), Some("a"));
+        assert_eq!(base.select("a")[1].rules[0].key, "left");
+
+        ::resolve_vars(&mut base);
+        assert_eq!(base.select("a")[0].get("color"), Some("blue"));
+    }
+
+    #[test]
+    fn compares_ignoring_whitespace() {
+        let spaced = parse("h1, h2 > p { color: red; margin: 0  1px }\n@media screen { a { top: 0 } }");
+        let tight = parse("h2  >  p,h1{color:red;margin:0 1px;}@media  screen{a{top:0;}}");
+        assert!(spaced != tight);
+        assert!(spaced.semantically_eq(&tight));
+
+        assert!(!spaced.semantically_eq(&parse("h1, h2 > p { color: blue; margin: 0 1px }\n@media screen { a { top: 0 } }")));
+        assert!(!spaced.semantically_eq(&parse("h1, h2 > p { color: red; margin: 0 1px }")));
+        assert!(!parse("a {} b {}").semantically_eq(&parse("b {} a {}")));
+    }
+
+    #[test]
+    fn reads_attribute_selectors() {
+        let root = parse("input[type=\"text\"], a[href^=\"https\"] { color: red; } a[title=\"}{,]\"] { b: c; } [data-x~='y'] , [lang|=en] { d: e; }");
+        assert_eq!(root.rule_sets.len(), 3);
+        assert_eq!(selectors(&root.rule_sets[0]), vec!["input[type=\"text\"]", "a[href^=\"https\"]"]);
+        assert_eq!(selectors(&root.rule_sets[1]), vec!["a[title=\"}{,]\"]"]);
+        assert_eq!(root.rule_sets[1].rules[0].key, "b");
+        assert_eq!(selectors(&root.rule_sets[2]), vec!["[data-x~='y']", "[lang|=en]"]);
+        assert!(::parse_str("a[x").is_err());
+    }
+
+    #[test]
+    fn each_parse_starts_afresh() {
+        let mut parser = CssParser::new();
+        assert!(parser.parse_bytes("a { b: c }\nd {").is_err());
+        assert_eq!(parser.position(), (2, 3));
+
+        let root = parser.parse_bytes("e { f: g }").unwrap();
+        assert_eq!(root.rule_sets.len(), 1);
+        assert_eq!(selectors(&root.rule_sets[0]), vec!["e"]);
+        assert_eq!(parser.position(), (1, 10));
+
+        let error = parser.parse_bytes("h {\n  i: \"j").unwrap_err();
+        assert_eq!(error.to_string(), "unterminated string at line 2, column 6");
+    }
+
+    #[test]
+    fn finds_the_end_of_the_last_escape() {
+        assert_eq!(escaped_end("abc"), None);
+        assert_eq!(escaped_end("a\\ b"), Some(3));
+        assert_eq!(escaped_end("\\\\x\\é"), Some(6));
+    }
+}
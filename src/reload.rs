@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use error::CssError;
+use paint::{Vertex, paint};
+use parse::CssRoot;
+
+// A stylesheet on disk together with the vertices painted from it for a window of
+// `viewport` size. A failed reload keeps the last good state, so a typo mid-edit does
+// not blank the window.
+#[derive(Debug)]
+pub struct LiveStylesheet {
+    path: PathBuf,
+    viewport: (f32, f32),
+    root: CssRoot,
+    vertices: Vec<Vertex>,
+}
+impl LiveStylesheet {
+    pub fn load<P: AsRef<Path>>(path : P, viewport : (f32, f32)) -> Result<LiveStylesheet, CssError> {
+        let path = path.as_ref().to_path_buf();
+        let root = ::parse_file(&path)?;
+        let vertices = paint(&root, viewport);
+
+        return Ok(LiveStylesheet {path, viewport, root, vertices});
+    }
+
+    pub fn reload(&mut self) -> Result<(), CssError> {
+        let root = ::parse_file(&self.path)?;
+        self.vertices = paint(&root, self.viewport);
+        self.root = root;
+        return Ok(());
+    }
+
+    // Paints the stylesheet again for a window of the new size.
+    pub fn resize(&mut self, viewport : (f32, f32)) {
+        self.viewport = viewport;
+        self.vertices = paint(&self.root, viewport);
+    }
+
+    pub fn path(&self) -> &Path {
+        return &self.path;
+    }
+
+    pub fn root(&self) -> &CssRoot {
+        return &self.root;
+    }
+
+    pub fn vertices(&self) -> &[Vertex] {
+        return &self.vertices;
+    }
+}
+
+// Parsed stylesheets keyed by a hash of their source, so reading back a file that
+// hasn't changed since it was last parsed costs a hash and a compare instead of a parse.
+#[derive(Debug, Default)]
+pub struct StyleCache {
+    // The source is kept with its root, so on a hash collision the new source is parsed
+    // and replaces the entry instead of being given another stylesheet.
+    roots: HashMap<u64, (String, CssRoot)>,
+    parses: usize,
+}
+impl StyleCache {
+    pub fn new() -> StyleCache {
+        return StyleCache::default();
+    }
+
+    // Sources that fail to parse are not cached, so they are parsed again next time.
+    pub fn get_or_parse(&mut self, source : &str) -> Result<&CssRoot, CssError> {
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+
+        let entry = match self.roots.entry(hasher.finish()) {
+            Entry::Occupied(entry) if entry.get().0 == source => entry.into_mut(),
+            Entry::Occupied(mut entry) => {
+                self.parses += 1;
+                entry.insert((source.to_string(), ::parse_str(source)?));
+                entry.into_mut()
+            },
+            Entry::Vacant(entry) => {
+                self.parses += 1;
+                entry.insert((source.to_string(), ::parse_str(source)?))
+            }
+        };
+        return Ok(&entry.1);
+    }
+
+    // How many times `get_or_parse` has had to parse.
+    pub fn parses(&self) -> usize {
+        return self.parses;
+    }
+
+    pub fn len(&self) -> usize {
+        return self.roots.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.roots.is_empty();
+    }
+
+    pub fn clear(&mut self) {
+        self.roots.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(source : &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+        return hasher.finish();
+    }
+
+    #[test]
+    fn parses_each_source_once() {
+        let mut cache = StyleCache::new();
+        cache.get_or_parse("a { b: c }").unwrap();
+        cache.get_or_parse("a { b: c }").unwrap();
+        assert!(cache.get_or_parse("a { b: c } }").is_err());
+        assert_eq!(cache.parses(), 2);
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn hash_collisions_parse_the_new_source() {
+        let mut cache = StyleCache::new();
+        let other = ::parse_str("x { y: z }").unwrap();
+        cache.roots.insert(hash("a { b: c }"), ("x { y: z }".to_string(), other));
+
+        let root = cache.get_or_parse("a { b: c }").unwrap();
+        assert_eq!(root.rule_sets[0].selectors, vec!["a".to_string()]);
+        assert_eq!(cache.parses(), 1);
+    }
+
+    #[test]
+    fn failed_reloads_keep_the_last_good_state() {
+        let name = format!("painter_{}_failed_reloads_keep_the_last_good_state.css", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, "a { width: 10px; height: 10px; background-color: red }").unwrap();
+        let mut live = LiveStylesheet::load(&path, (100.0, 100.0)).unwrap();
+        assert_eq!(live.vertices()[0].color, [1.0, 0.0, 0.0, 1.0]);
+
+        std::fs::write(&path, "a { width: 10px; height: 10px; background-color: blue }").unwrap();
+        live.reload().unwrap();
+        assert_eq!(live.vertices()[0].color, [0.0, 0.0, 1.0, 1.0]);
+
+        std::fs::write(&path, "a { width: 10px; } }").unwrap();
+        assert!(live.reload().is_err());
+        assert_eq!(live.vertices()[0].color, [0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(live.root().rule_sets[0].get("background-color"), Some("blue"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn resize_paints_for_the_new_viewport() {
+        let name = format!("painter_{}_resize_paints_for_the_new_viewport.css", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, "a { width: 50%; height: 10px; background-color: red }").unwrap();
+        let mut live = LiveStylesheet::load(&path, (100.0, 100.0)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(live.vertices()[1].pos, [50.0, 0.0]);
+        live.resize((300.0, 100.0));
+        assert_eq!(live.vertices()[1].pos, [150.0, 0.0]);
+    }
+}
@@ -0,0 +1,390 @@
+use std::mem;
+
+pub type Specificity = (u32, u32, u32);
+
+const LEGACY_PSEUDO_ELEMENTS: &[&str] = &["before", "after", "first-line", "first-letter"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Combinator {
+    Descendant,
+    Child,
+    Adjacent,
+    Sibling,
+}
+
+// The functional pseudo-classes that are parsed into their arguments. Matching them
+// isn't supported yet.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PseudoClass {
+    Not(Box<Selector>),
+    // Matches every `a`th child starting from the `b`th, as in `2n+1`.
+    NthChild {a: i32, b: i32},
+}
+
+// A run of simple selectors like `div.note:hover` or `*`, with the
+// combinator joining it to the compound before it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Compound {
+    pub combinator: Option<Combinator>,
+    pub text: String,
+    // Those of `PseudoClass` found in `text`, in order.
+    pub pseudo_classes: Vec<PseudoClass>,
+}
+impl Compound {
+    pub fn is_universal(&self) -> bool {
+        return self.text == "*";
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selector {
+    pub compounds: Vec<Compound>,
+}
+
+// Parses a single (ungrouped) selector into its compounds. Returns None for
+// an empty selector, a combinator with nothing on one side, like `div >`, or
+// unbalanced brackets and arguments `PseudoClass` can't hold, like `:not(a, b)`.
+pub fn parse_selector(selector : &str) -> Option<Selector> {
+    let chars: Vec<char> = selector.trim().chars().collect();
+    let mut compounds = Vec::new();
+    let mut combinator = None;
+    let mut text = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let explicit = match c {
+            '>' => Some(Combinator::Child),
+            '+' => Some(Combinator::Adjacent),
+            '~' => Some(Combinator::Sibling),
+            _ => None
+        };
+
+        if explicit.is_some() || c.is_whitespace() {
+            if !text.is_empty() {
+                compounds.push(compound(combinator, mem::take(&mut text))?);
+                combinator = Some(Combinator::Descendant);
+            }
+            if explicit.is_some() {
+                // An explicit combinator needs a compound before it and may not follow another.
+                if compounds.is_empty() || combinator != Some(Combinator::Descendant) {
+                    return None;
+                }
+                combinator = explicit;
+            }
+            i += 1;
+            continue;
+        }
+
+        if c == ',' || c == ')' || c == ']' {
+            return None;
+        }
+
+        let end = match c {
+            '[' => block_end(&chars, i, '[', ']')?,
+            '(' => block_end(&chars, i, '(', ')')?,
+            _ => i + 1
+        };
+        text.extend(&chars[i..end]);
+        i = end;
+    }
+
+    if text.is_empty() {
+        return None;
+    }
+    compounds.push(compound(combinator, text)?);
+    return Some(Selector {compounds});
+}
+
+fn compound(combinator : Option<Combinator>, text : String) -> Option<Compound> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut pseudo_classes = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            '[' => i = skip_block(&chars, i, '[', ']'),
+            ':' => {
+                // Pseudo-elements like `::part(label)` are no pseudo-classes.
+                let element = chars.get(i + 1) == Some(&':');
+                let start = if element { i + 2 } else { i + 1 };
+                let end = skip_ident(&chars, start);
+                let name: String = chars[start..end].iter().collect::<String>().to_ascii_lowercase();
+                i = end;
+
+                if chars.get(i) == Some(&'(') {
+                    let (args, close) = parenthesized(&chars, i);
+                    i = close;
+
+                    match name.as_str() {
+                        _ if element => {},
+                        "not" => pseudo_classes.push(PseudoClass::Not(Box::new(parse_selector(&args)?))),
+                        "nth-child" => {
+                            let (a, b) = parse_nth(&args)?;
+                            pseudo_classes.push(PseudoClass::NthChild {a, b});
+                        },
+                        _ => {}
+                    }
+                }
+            },
+            _ => i += 1
+        }
+    }
+
+    return Some(Compound {combinator, text, pseudo_classes});
+}
+
+// The `An+B` argument of `:nth-child()`, with `odd` and `even` as `2n+1` and `2n`.
+fn parse_nth(args : &str) -> Option<(i32, i32)> {
+    let args = args.trim().to_ascii_lowercase();
+    match args.as_str() {
+        "odd" => return Some((2, 1)),
+        "even" => return Some((2, 0)),
+        _ => {}
+    }
+
+    let n = match args.find('n') {
+        Some(n) => n,
+        None => return Some((0, args.parse().ok()?))
+    };
+    let a = match &args[..n] {
+        "" | "+" => 1,
+        "-" => -1,
+        a => a.parse().ok()?
+    };
+
+    // Whitespace may go around the sign of `b`, but not between the sign and its digits.
+    let rest = args[n + 1..].trim_start();
+    if rest.is_empty() {
+        return Some((a, 0));
+    }
+    let sign = match rest.chars().next() {
+        Some('+') => 1,
+        Some('-') => -1,
+        _ => return None
+    };
+    let b = rest[1..].trim_start();
+    if !b.starts_with(|c : char| c.is_ascii_digit()) {
+        return None;
+    }
+    return Some((a, sign * b.parse::<i32>().ok()?));
+}
+
+// Counts (id, class, type) per the CSS selectors spec. Grouped selectors
+// should be split on ',' and scored individually before calling this.
+pub fn specificity(selector : &str) -> Specificity {
+    let chars: Vec<char> = selector.chars().collect();
+    let (mut a, mut b, mut c) = (0, 0, 0);
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '#' => {
+                a += 1;
+                i = skip_ident(&chars, i + 1);
+            },
+            '.' => {
+                b += 1;
+                i = skip_ident(&chars, i + 1);
+            },
+            '[' => {
+                b += 1;
+                i = skip_block(&chars, i, '[', ']');
+            },
+            ':' if chars.get(i + 1) == Some(&':') => {
+                c += 1;
+                i = skip_ident(&chars, i + 2);
+                if chars.get(i) == Some(&'(') {
+                    i = skip_block(&chars, i, '(', ')');
+                }
+            },
+            ':' => {
+                let end = skip_ident(&chars, i + 1);
+                let name: String = chars[i + 1..end].iter().collect::<String>().to_ascii_lowercase();
+                i = end;
+
+                if chars.get(i) == Some(&'(') {
+                    let (args, close) = parenthesized(&chars, i);
+                    i = close;
+
+                    let (x, y, z) = match name.as_str() {
+                        "not" | "is" | "has" => max_specificity(&args),
+                        "where" => (0, 0, 0),
+                        _ => (0, 1, 0)
+                    };
+                    a += x;
+                    b += y;
+                    c += z;
+                } else if LEGACY_PSEUDO_ELEMENTS.contains(&name.as_str()) {
+                    c += 1;
+                } else {
+                    b += 1;
+                }
+            },
+            x if is_ident_char(x) => {
+                c += 1;
+                i = skip_ident(&chars, i);
+            },
+            _ => i += 1
+        }
+    }
+
+    return (a, b, c);
+}
+
+fn max_specificity(selectors : &str) -> Specificity {
+    return selectors.split(',').map(specificity).max().unwrap_or((0, 0, 0));
+}
+
+fn is_ident_char(c : char) -> bool {
+    return c.is_alphanumeric() || c == '-' || c == '_' || c == '\\' || !c.is_ascii();
+}
+
+fn skip_ident(chars : &[char], start : usize) -> usize {
+    let mut i = start;
+    while i < chars.len() && is_ident_char(chars[i]) {
+        i += 1;
+    }
+    return i;
+}
+
+// Returns the index just past the bracket matching the one at `start`, or the
+// end for an unclosed one.
+fn skip_block(chars : &[char], start : usize, open : char, close : char) -> usize {
+    return block_end(chars, start, open, close).unwrap_or(chars.len());
+}
+
+// The arguments inside the parentheses opening at `start`, and the index just past them.
+// An unclosed `(` takes the rest of the selector as its arguments.
+fn parenthesized(chars : &[char], start : usize) -> (String, usize) {
+    let (args_end, close) = match block_end(chars, start, '(', ')') {
+        Some(close) => (close - 1, close),
+        None => (chars.len(), chars.len())
+    };
+    return (chars[start + 1..args_end].iter().collect(), close);
+}
+
+fn block_end(chars : &[char], start : usize, open : char, close : char) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut i = start;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {},
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == open => depth += 1,
+            None if c == close => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            },
+            None => {}
+        }
+        i += 1;
+    }
+
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn specificity_of_spec_examples() {
+        let examples = [
+            ("*", (0, 0, 0)),
+            ("li", (0, 0, 1)),
+            ("ul li", (0, 0, 2)),
+            ("ul ol+li", (0, 0, 3)),
+            ("h1 + *[rel=up]", (0, 1, 1)),
+            ("ul ol li.red", (0, 1, 3)),
+            ("li.red.level", (0, 2, 1)),
+            ("#x34y", (1, 0, 0)),
+            ("#s12:not(FOO)", (1, 0, 1)),
+            (".foo :is(.bar, #baz)", (1, 1, 0)),
+            ("div.foo#bar:hover", (1, 2, 1)),
+            ("a::before", (0, 0, 2)),
+            ("a:before", (0, 0, 2)),
+            ("a[href=\"x]y\"]", (0, 1, 1)),
+        ];
+        for &(selector, expected) in examples.iter() {
+            assert_eq!(specificity(selector), expected, "{}", selector);
+        }
+    }
+
+    fn shape(selector : &str) -> Vec<(Option<Combinator>, String)> {
+        return parse_selector(selector).unwrap().compounds.into_iter()
+            .map(|compound| (compound.combinator, compound.text))
+            .collect();
+    }
+
+    #[test]
+    fn parses_combinators() {
+        let compound = |combinator, text : &str| (combinator, text.to_string());
+        assert_eq!(shape("*"), vec![compound(None, "*")]);
+        assert!(parse_selector("*").unwrap().compounds[0].is_universal());
+        assert_eq!(shape("div p"), vec![compound(None, "div"), compound(Some(Combinator::Descendant), "p")]);
+        assert_eq!(shape("div>p"), vec![compound(None, "div"), compound(Some(Combinator::Child), "p")]);
+        assert_eq!(shape("h1 + p ~ a"), vec![
+            compound(None, "h1"),
+            compound(Some(Combinator::Adjacent), "p"),
+            compound(Some(Combinator::Sibling), "a"),
+        ]);
+        assert_eq!(shape("a[title=\"x > y\"] :not(a > b)"), vec![
+            compound(None, "a[title=\"x > y\"]"),
+            compound(Some(Combinator::Descendant), ":not(a > b)"),
+        ]);
+    }
+
+    #[test]
+    fn rejects_dangling_combinators() {
+        for malformed in ["div >", "> p", "a > > p", ""].iter() {
+            assert!(parse_selector(malformed).is_none(), "{}", malformed);
+        }
+    }
+
+    #[test]
+    fn specificity_counts_pseudo_class_arguments() {
+        assert_eq!(specificity("a:not(#b, .c)"), (1, 0, 1));
+        assert_eq!(specificity("a:where(#b)"), (0, 0, 1));
+        assert_eq!(specificity("a:nth-child(2n)"), (0, 1, 1));
+    }
+
+    #[test]
+    fn specificity_of_unclosed_arguments() {
+        assert_eq!(specificity("a:not("), (0, 0, 1));
+        assert_eq!(specificity("a:not(.b"), (0, 1, 1));
+        assert_eq!(specificity("a:nth-child("), (0, 1, 1));
+    }
+
+    #[test]
+    fn parse_selector_with_unclosed_arguments() {
+        assert!(parse_selector("a:not(").is_none());
+        assert!(parse_selector("a:nth-child(").is_none());
+    }
+
+    #[test]
+    fn parses_not_and_nth_child() {
+        let selector = parse_selector("a:not(.x)").unwrap();
+        assert_eq!(selector.compounds[0].pseudo_classes, vec![PseudoClass::Not(Box::new(parse_selector(".x").unwrap()))]);
+
+        let forms = [("2n+1", (2, 1)), ("odd", (2, 1)), ("even", (2, 0)), ("-n + 3", (-1, 3)), ("+5", (0, 5)), ("-2N", (-2, 0))];
+        for &(args, (a, b)) in forms.iter() {
+            let selector = parse_selector(&format!("li:nth-child({})", args)).unwrap();
+            assert_eq!(selector.compounds[0].pseudo_classes, vec![PseudoClass::NthChild {a, b}], "{}", args);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_pseudo_class_arguments() {
+        for malformed in ["li:nth-child(2 n)", "li:nth-child(n+-1)", "li:nth-child(x)", "a:not()", "a:not(a, b)", "a:not(b"].iter() {
+            assert!(parse_selector(malformed).is_none(), "{}", malformed);
+        }
+    }
+}
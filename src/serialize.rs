@@ -0,0 +1,282 @@
+use std::fmt;
+
+use parse::{CssRoot, CssFontFace, CssKeyframes, CssMediaQuery, CssRuleSet, CssRule, unescaped_find};
+
+const INDENT: &str = "    ";
+
+impl fmt::Display for CssRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.key, self.value)?;
+        if self.important {
+            write!(f, " !important")?;
+        }
+        for flag in self.flags.iter() {
+            write!(f, " !{}", flag)?;
+        }
+        return Ok(());
+    }
+}
+
+impl fmt::Display for CssRuleSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write_rule_set(f, self, INDENT, 0);
+    }
+}
+
+impl fmt::Display for CssRoot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write_root(f, self, INDENT);
+    }
+}
+
+// Pretty prints like `Display`, indenting nested lines with `indent`, such as "  " or "\t".
+pub fn to_string_with_indent(root : &CssRoot, indent : &str) -> String {
+    let mut out = String::new();
+    write_root(&mut out, root, indent).expect("writing to a String cannot fail");
+    return out;
+}
+
+fn write_root(f : &mut dyn fmt::Write, root : &CssRoot, indent : &str) -> fmt::Result {
+    let mut written = false;
+
+    if let Some(ref charset) = root.charset {
+        writeln!(f, "@charset {};", quoted(charset))?;
+        written = true;
+    }
+
+    if !root.namespaces.is_empty() {
+        separate(f, &mut written)?;
+        for (prefix, uri) in root.namespaces.iter() {
+            writeln!(f, "{};", namespace_rule(prefix, uri))?;
+        }
+    }
+
+    if !root.imports.is_empty() {
+        separate(f, &mut written)?;
+        for import in root.imports.iter() {
+            writeln!(f, "@import {};", quoted(import))?;
+        }
+    }
+
+    for rule_set in root.rule_sets.iter() {
+        separate(f, &mut written)?;
+        write_rule_set(f, rule_set, indent, 0)?;
+    }
+
+    for media in root.media_queries.iter() {
+        separate(f, &mut written)?;
+        write_media_query(f, media, indent)?;
+    }
+
+    for font_face in root.font_faces.iter() {
+        separate(f, &mut written)?;
+        write_font_face(f, font_face, indent)?;
+    }
+
+    for keyframes in root.keyframes.iter() {
+        separate(f, &mut written)?;
+        write_keyframes(f, keyframes, indent)?;
+    }
+
+    return Ok(());
+}
+
+// Puts a blank line between top-level blocks.
+fn separate(f : &mut dyn fmt::Write, written : &mut bool) -> fmt::Result {
+    if *written {
+        writeln!(f)?;
+    }
+    *written = true;
+    return Ok(());
+}
+
+// Quotes values read from a string or url. Only those read from single quotes can hold
+// a `"` that isn't escaped, and they go back in single quotes.
+fn quoted(value : &str) -> String {
+    if unescaped_find(value, '"').is_some() {
+        return format!("'{}'", value);
+    }
+    return format!("\"{}\"", value);
+}
+
+fn namespace_rule(prefix : &Option<String>, uri : &str) -> String {
+    return match *prefix {
+        Some(ref prefix) => format!("@namespace {} {}", prefix, quoted(uri)),
+        None => format!("@namespace {}", quoted(uri))
+    }
+}
+
+fn write_rule_set(f : &mut dyn fmt::Write, rule_set : &CssRuleSet, indent : &str, depth : usize) -> fmt::Result {
+    if rule_set.children.is_empty() {
+        return write_block(f, &rule_set.selectors.join(", "), &rule_set.rules, indent, depth);
+    }
+
+    let outer = indent.repeat(depth);
+    let inner = indent.repeat(depth + 1);
+
+    writeln!(f, "{}{} {{", outer, rule_set.selectors.join(", "))?;
+    for rule in rule_set.rules.iter() {
+        writeln!(f, "{}{};", inner, rule)?;
+    }
+    for child in rule_set.children.iter() {
+        write_rule_set(f, child, indent, depth + 1)?;
+    }
+    return writeln!(f, "{}}}", outer);
+}
+
+fn write_media_query(f : &mut dyn fmt::Write, media : &CssMediaQuery, indent : &str) -> fmt::Result {
+    writeln!(f, "@media {} {{", media.condition)?;
+    for rule_set in media.rule_sets.iter() {
+        write_rule_set(f, rule_set, indent, 1)?;
+    }
+    return writeln!(f, "}}");
+}
+
+fn write_font_face(f : &mut dyn fmt::Write, font_face : &CssFontFace, indent : &str) -> fmt::Result {
+    return write_block(f, "@font-face", &font_face.descriptors, indent, 0);
+}
+
+fn write_keyframes(f : &mut dyn fmt::Write, keyframes : &CssKeyframes, indent : &str) -> fmt::Result {
+    writeln!(f, "@keyframes {} {{", keyframes.name)?;
+    for (stop, rules) in keyframes.frames.iter() {
+        write_block(f, stop, rules, indent, 1)?;
+    }
+    return writeln!(f, "}}");
+}
+
+fn write_block(f : &mut dyn fmt::Write, prelude : &str, rules : &[CssRule], indent : &str, depth : usize) -> fmt::Result {
+    let outer = indent.repeat(depth);
+    let inner = indent.repeat(depth + 1);
+
+    writeln!(f, "{}{} {{", outer, prelude)?;
+    for rule in rules.iter() {
+        writeln!(f, "{}{};", inner, rule)?;
+    }
+    return writeln!(f, "{}}}", outer);
+}
+
+pub fn minify(root : &CssRoot) -> String {
+    let mut out = String::new();
+
+    if let Some(ref charset) = root.charset {
+        out.push_str(&format!("@charset {};", quoted(charset)));
+    }
+
+    for (prefix, uri) in root.namespaces.iter() {
+        out.push_str(&namespace_rule(prefix, uri));
+        out.push(';');
+    }
+
+    for import in root.imports.iter() {
+        out.push_str(&format!("@import {};", quoted(import)));
+    }
+
+    for rule_set in root.rule_sets.iter() {
+        minify_rule_set(&mut out, rule_set);
+    }
+
+    for media in root.media_queries.iter() {
+        out.push_str(&format!("@media {}{{", media.condition));
+        for rule_set in media.rule_sets.iter() {
+            minify_rule_set(&mut out, rule_set);
+        }
+        out.push('}');
+    }
+
+    for font_face in root.font_faces.iter() {
+        minify_block(&mut out, "@font-face", &font_face.descriptors);
+    }
+
+    for keyframes in root.keyframes.iter() {
+        out.push_str(&format!("@keyframes {}{{", keyframes.name));
+        for (stop, rules) in keyframes.frames.iter() {
+            minify_block(&mut out, &minify_stop(stop), rules);
+        }
+        out.push('}');
+    }
+
+    return out;
+}
+
+// Stops are joined with ", " when parsed. Anything that doesn't split back into plain stops
+// like `from` or `50%` is left as it was, so it reads back the same.
+fn minify_stop(stop : &str) -> String {
+    let plain = |part : &str| !part.is_empty() && part.chars().all(|c| c.is_alphanumeric() || c == '.' || c == '%');
+    if stop.split(", ").all(plain) {
+        return stop.replace(", ", ",");
+    }
+    return stop.to_string();
+}
+
+fn minify_rule_set(out : &mut String, rule_set : &CssRuleSet) {
+    minify_block(out, &rule_set.selectors.join(","), &rule_set.rules);
+    if rule_set.children.is_empty() {
+        return;
+    }
+
+    // Nested rule sets go inside the parent's block, after its declarations.
+    out.pop();
+    if !rule_set.rules.is_empty() {
+        out.push(';');
+    }
+    for child in rule_set.children.iter() {
+        minify_rule_set(out, child);
+    }
+    out.push('}');
+}
+
+fn minify_block(out : &mut String, prelude : &str, rules : &[CssRule]) {
+    let declarations: Vec<String> = rules.iter()
+        .map(|rule| {
+            let important = if rule.important { "!important" } else { "" };
+            let flags: String = rule.flags.iter().map(|flag| format!("!{}", flag)).collect();
+            format!("{}:{}{}{}", rule.key, rule.value, important, flags)
+        })
+        .collect();
+
+    out.push_str(prelude);
+    out.push('{');
+    out.push_str(&declarations.join(";"));
+    out.push('}');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STYLESHEET: &str = "@import url(x.css);\na, b { color: red !important; content: 'q'; c: \"d\" }\nc {}\n\
+        @media (max-width: 1px) { d { e: f } }\n@font-face { src: url(a.woff) }\n@keyframes k { from { o: 0 } 50%, 60% { o: 1 } }";
+
+    #[test]
+    fn display_roundtrips() {
+        let root = ::parse_str(STYLESHEET).unwrap();
+        assert_eq!(::parse_str(&root.to_string()).unwrap(), root);
+        assert_eq!(root.rule_sets[0].to_string(), "a, b {\n    color: red !important;\n    content: 'q';\n    c: \"d\";\n}\n");
+    }
+
+    #[test]
+    fn minifies() {
+        let root = ::parse_str(STYLESHEET).unwrap();
+        let minified = minify(&root);
+        assert_eq!(minified, "@import \"x.css\";a,b{color:red!important;content:'q';c:\"d\"}c{}@media (max-width: 1px){d{e:f}}\
+            @font-face{src:url(a.woff)}@keyframes k{from{o:0}50%,60%{o:1}}");
+        assert_eq!(::parse_str(&minified).unwrap(), root);
+    }
+
+    #[test]
+    fn keeps_quote_styles() {
+        let root = ::parse_str("a { content: 'x'; b: \"it's\"; c: 'say \"hi\"' }").unwrap();
+        let text = root.to_string();
+        assert!(text.contains("content: 'x';"));
+        assert!(text.contains("b: \"it's\";"));
+        assert!(text.contains("c: 'say \"hi\"';"));
+        assert_eq!(::parse_str(&text).unwrap(), root);
+    }
+
+    #[test]
+    fn indents_with_the_given_string() {
+        let root = ::parse_str("a { b: c; } @media x { d { e: f; } }").unwrap();
+        assert_eq!(to_string_with_indent(&root, "  "), "a {\n  b: c;\n}\n\n@media x {\n  d {\n    e: f;\n  }\n}\n");
+        assert_eq!(to_string_with_indent(&root, "    "), root.to_string());
+    }
+}
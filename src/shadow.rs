@@ -0,0 +1,118 @@
+use color::{Color, parse_color};
+use length::{Length, parse_length};
+use shorthand::split_components;
+use value::split_value_list;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// One shadow of a `box-shadow` list. Omitted blur and spread are zero, and a missing
+// color, `currentcolor` included, is None for the painter to take from `color`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BoxShadow {
+    pub offset_x: Length,
+    pub offset_y: Length,
+    pub blur: Length,
+    pub spread: Length,
+    pub color: Option<Color>,
+    pub inset: bool,
+}
+
+// Reads `[inset] <x> <y> [<blur> [<spread>]] [<color>]` shadows, separated by commas,
+// with `none` as no shadows. `inset` and the color may come first or last.
+pub fn parse_box_shadow(value : &str) -> Option<Vec<BoxShadow>> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("none") {
+        return Some(Vec::new());
+    }
+    return split_value_list(value).iter().map(|shadow| box_shadow(shadow)).collect();
+}
+
+fn box_shadow(shadow : &str) -> Option<BoxShadow> {
+    let mut lengths = Vec::new();
+    let mut color = None;
+    let mut has_color = false;
+    let mut inset = false;
+    // The lengths have to follow each other, so none can come after a keyword or color
+    // that follows them.
+    let mut lengths_done = false;
+
+    for component in split_components(shadow) {
+        let lower = component.to_ascii_lowercase();
+        if let Some(length) = parse_length(component).filter(|_| !lengths_done) {
+            lengths.push(length);
+            continue;
+        }
+
+        if lower == "inset" && !inset {
+            inset = true;
+        } else if !has_color && (lower == "currentcolor" || parse_color(component).is_some()) {
+            color = parse_color(component);
+            has_color = true;
+        } else {
+            return None;
+        }
+        lengths_done = !lengths.is_empty();
+    }
+
+    if lengths.len() < 2 || lengths.len() > 4 || lengths.contains(&Length::Auto) {
+        return None;
+    }
+    let zero = Length::Px(0.0);
+    let blur = lengths.get(2).cloned().unwrap_or(zero);
+    if is_negative(blur) {
+        return None;
+    }
+
+    return Some(BoxShadow {
+        offset_x: lengths[0],
+        offset_y: lengths[1],
+        blur,
+        spread: lengths.get(3).cloned().unwrap_or(zero),
+        color,
+        inset,
+    });
+}
+
+fn is_negative(length : Length) -> bool {
+    return match length {
+        Length::Px(value) | Length::Em(value) | Length::Rem(value) | Length::Percent(value)
+            | Length::Vh(value) | Length::Vw(value) => value < 0.0,
+        Length::Auto => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_shadows() {
+        assert_eq!(parse_box_shadow("2px 4px 6px 1px rgba(0,0,0,0.5)"), Some(vec![BoxShadow {
+            offset_x: Length::Px(2.0),
+            offset_y: Length::Px(4.0),
+            blur: Length::Px(6.0),
+            spread: Length::Px(1.0),
+            color: parse_color("rgba(0,0,0,0.5)"),
+            inset: false,
+        }]));
+
+        let shadows = parse_box_shadow("1px 1px 2px black inset, -1em 2px currentColor").unwrap();
+        assert_eq!(shadows.len(), 2);
+        assert!(shadows[0].inset && !shadows[1].inset);
+        assert_eq!(shadows[1].offset_x, Length::Em(-1.0));
+        assert_eq!(shadows[1].blur, Length::Px(0.0));
+        assert_eq!(shadows[1].color, None);
+        assert_eq!(parse_box_shadow("NONE"), Some(vec![]));
+    }
+
+    #[test]
+    fn rejects_malformed_shadows() {
+        let malformed = ["1px", "1px 2px 3px 4px 5px", "1px red 2px", "1px inset 2px", "inset inset 1px 1px", "1px 1px -2px",
+            "1px 1px red blue", "auto 1px", "1px 1px,"];
+        for shadow in malformed.iter() {
+            assert_eq!(parse_box_shadow(shadow), None, "{}", shadow);
+        }
+    }
+}
@@ -0,0 +1,453 @@
+use std::mem;
+
+use color::parse_color;
+use length::parse_length;
+use parse::{CssRoot, CssRule, CssRuleSet};
+use value::split_value_list;
+
+const BORDER_STYLES: &[&str] = &[
+    "none", "hidden", "dotted", "dashed", "solid", "double", "groove", "ridge", "inset", "outset"
+];
+
+const BACKGROUND_REPEATS: &[&str] = &["repeat", "repeat-x", "repeat-y", "no-repeat", "space", "round"];
+
+const BACKGROUND_POSITIONS: &[&str] = &["left", "right", "top", "bottom", "center"];
+
+const BACKGROUND_SIZES: &[&str] = &["auto", "cover", "contain"];
+
+const FONT_STYLES: &[&str] = &["normal", "italic", "oblique"];
+
+const FONT_WEIGHTS: &[&str] = &[
+    "normal", "bold", "bolder", "lighter", "100", "200", "300", "400", "500", "600", "700", "800", "900"
+];
+
+const FONT_SIZES: &[&str] = &[
+    "xx-small", "x-small", "small", "medium", "large", "x-large", "xx-large", "larger", "smaller"
+];
+
+// Expands every rule set, including those inside `@media` blocks and nested ones.
+pub fn expand_shorthands(root : &mut CssRoot) {
+    let media_rule_sets = root.media_queries.iter_mut().flat_map(|media| media.rule_sets.iter_mut());
+    for rule_set in root.rule_sets.iter_mut().chain(media_rule_sets) {
+        expand_nested(rule_set);
+    }
+}
+
+fn expand_nested(rule_set : &mut CssRuleSet) {
+    expand_rule_set(rule_set);
+    for child in rule_set.children.iter_mut() {
+        expand_nested(child);
+    }
+}
+
+pub(crate) fn expand_rule_set(rule_set : &mut CssRuleSet) {
+    let rules = mem::take(&mut rule_set.rules);
+    for rule in rules {
+        match expand_rule(&rule) {
+            Some(longhands) => rule_set.rules.extend(longhands),
+            None => rule_set.rules.push(rule)
+        }
+    }
+}
+
+fn expand_rule(rule : &CssRule) -> Option<Vec<CssRule>> {
+    return match rule.key.as_str() {
+        "margin" | "padding" | "border-width" | "border-style" | "border-color" | "inset" => expand_box(rule),
+        "border" => expand_border(rule),
+        "background" => expand_background(rule),
+        "font" => expand_font(rule),
+        _ => None
+    }
+}
+
+// Applies the 1-4 value top/right/bottom/left rules shared by box shorthands.
+fn expand_box(rule : &CssRule) -> Option<Vec<CssRule>> {
+    let values = split_components(&rule.value);
+    let (top, right, bottom, left) = match values.len() {
+        1 => (values[0], values[0], values[0], values[0]),
+        2 => (values[0], values[1], values[0], values[1]),
+        3 => (values[0], values[1], values[2], values[1]),
+        4 => (values[0], values[1], values[2], values[3]),
+        _ => return None
+    };
+
+    // The side goes after `border`, as in `border-top-width`, and `inset` sets the sides
+    // themselves.
+    let key = |side : &str| match rule.key.strip_prefix("border-") {
+        Some(property) => format!("border-{}-{}", side, property),
+        None if rule.key == "inset" => side.to_string(),
+        None => format!("{}-{}", rule.key, side)
+    };
+
+    return Some(vec![
+        longhand(rule, &key("top"), top),
+        longhand(rule, &key("right"), right),
+        longhand(rule, &key("bottom"), bottom),
+        longhand(rule, &key("left"), left),
+    ]);
+}
+
+// Components may appear in any order; omitted ones reset to their initial values. Every
+// side is set, so `border` overrides the per-side longhands declared before it.
+fn expand_border(rule : &CssRule) -> Option<Vec<CssRule>> {
+    let mut width = None;
+    let mut style = None;
+    let mut color = None;
+
+    for component in split_components(&rule.value) {
+        let lower = component.to_ascii_lowercase();
+        if width.is_none() && is_border_width(&lower) {
+            width = Some(component);
+        } else if style.is_none() && BORDER_STYLES.contains(&lower.as_str()) {
+            style = Some(component);
+        } else if color.is_none() && (lower == "currentcolor" || parse_color(component).is_some()) {
+            color = Some(component);
+        } else {
+            return None;
+        }
+    }
+
+    let mut longhands = Vec::new();
+    for &(property, value) in [("width", width.unwrap_or("medium")), ("style", style.unwrap_or("none")),
+        ("color", color.unwrap_or("currentcolor"))].iter() {
+        for side in ["top", "right", "bottom", "left"].iter() {
+            longhands.push(longhand(rule, &format!("border-{}-{}", side, property), value));
+        }
+    }
+    return Some(longhands);
+}
+
+// background: [color] [image] [repeat] [position [/ size]], in any order. Only a single
+// layer is expanded, and omitted parts reset to their initial values.
+fn expand_background(rule : &CssRule) -> Option<Vec<CssRule>> {
+    if split_value_list(&rule.value).len() != 1 {
+        return None;
+    }
+
+    let components = split_slashes(split_components(&rule.value));
+    let mut color = None;
+    let mut image = None;
+    let mut repeat = None;
+    let mut position = None;
+    let mut size = None;
+    let mut i = 0;
+
+    while i < components.len() {
+        let lower = components[i].to_ascii_lowercase();
+        if position.is_none() && is_background_position(&lower) {
+            let end = run_end(&components, i, 4, is_background_position);
+            position = Some(components[i..end].join(" "));
+            i = end;
+
+            // The size can only follow the position, after a slash.
+            if components.get(i) == Some(&"/") {
+                let end = run_end(&components, i + 1, 2, is_background_size);
+                if end == i + 1 {
+                    return None;
+                }
+                size = Some(components[i + 1..end].join(" "));
+                i = end;
+            }
+            continue;
+        }
+
+        if repeat.is_none() && BACKGROUND_REPEATS.contains(&lower.as_str()) {
+            let end = run_end(&components, i, 2, |c| BACKGROUND_REPEATS.contains(&c));
+            repeat = Some(components[i..end].join(" "));
+            i = end;
+            continue;
+        }
+
+        if image.is_none() && (lower == "none" || lower.starts_with("url(") || lower.contains("gradient(")) {
+            image = Some(components[i]);
+        } else if color.is_none() && (lower == "currentcolor" || parse_color(components[i]).is_some()) {
+            color = Some(components[i]);
+        } else {
+            return None;
+        }
+        i += 1;
+    }
+
+    return Some(vec![
+        longhand(rule, "background-color", color.unwrap_or("transparent")),
+        longhand(rule, "background-image", image.unwrap_or("none")),
+        longhand(rule, "background-repeat", repeat.as_deref().unwrap_or("repeat")),
+        longhand(rule, "background-position", position.as_deref().unwrap_or("0% 0%")),
+        longhand(rule, "background-size", size.as_deref().unwrap_or("auto")),
+    ]);
+}
+
+fn is_background_position(value : &str) -> bool {
+    return BACKGROUND_POSITIONS.contains(&value) || parse_length(value).is_some();
+}
+
+fn is_background_size(value : &str) -> bool {
+    return BACKGROUND_SIZES.contains(&value) || parse_length(value).is_some();
+}
+
+// The end of the run of at most `max` components from `start` that `matches` takes,
+// compared in lowercase.
+fn run_end<F: Fn(&str) -> bool>(components : &[&str], start : usize, max : usize, matches : F) -> usize {
+    let mut end = start;
+    while end < components.len() && end - start < max && matches(&components[end].to_ascii_lowercase()) {
+        end += 1;
+    }
+    return end;
+}
+
+fn is_border_width(value : &str) -> bool {
+    return match value {
+        "thin" | "medium" | "thick" => true,
+        _ => parse_length(value).is_some()
+    }
+}
+
+// font: [style] [weight] size[/line-height] family
+fn expand_font(rule : &CssRule) -> Option<Vec<CssRule>> {
+    let components = split_components(&rule.value);
+    let mut style = None;
+    let mut weight = None;
+    let mut i = 0;
+
+    while i < components.len() {
+        let lower = components[i].to_ascii_lowercase();
+        if lower == "normal" {
+            // Ambiguous between style and weight, and both default to it anyway.
+        } else if style.is_none() && FONT_STYLES.contains(&lower.as_str()) {
+            style = Some(components[i]);
+        } else if weight.is_none() && FONT_WEIGHTS.contains(&lower.as_str()) {
+            weight = Some(components[i]);
+        } else {
+            break;
+        }
+        i += 1;
+    }
+
+    let mut size = *components.get(i)?;
+    let mut line_height = None;
+    i += 1;
+
+    if let Some(slash) = size.find('/') {
+        line_height = Some(&size[slash + 1..]);
+        size = &size[..slash];
+    } else if let Some(next) = components.get(i).and_then(|c| c.strip_prefix('/')) {
+        line_height = Some(next);
+        i += 1;
+    }
+
+    if line_height == Some("") {
+        line_height = components.get(i).cloned();
+        i += 1;
+    }
+
+    if !is_font_size(size) || line_height == Some("") || i >= components.len() {
+        return None;
+    }
+
+    let family = components[i..].join(" ");
+
+    return Some(vec![
+        longhand(rule, "font-style", style.unwrap_or("normal")),
+        longhand(rule, "font-weight", weight.unwrap_or("normal")),
+        longhand(rule, "font-size", size),
+        longhand(rule, "line-height", line_height.unwrap_or("normal")),
+        longhand(rule, "font-family", &family),
+    ]);
+}
+
+fn is_font_size(value : &str) -> bool {
+    let lower = value.to_ascii_lowercase();
+    return FONT_SIZES.contains(&lower.as_str()) || parse_length(&lower).is_some();
+}
+
+fn longhand(shorthand : &CssRule, key : &str, value : &str) -> CssRule {
+    let mut rule = CssRule::new(key, value);
+    rule.important = shorthand.important;
+    rule.flags = shorthand.flags.clone();
+    return rule;
+}
+
+// Splits the slashes outside parentheses off into components of their own, so
+// `center/cover` and `center / cover` both read as `center`, `/` and `cover`.
+fn split_slashes(components : Vec<&str>) -> Vec<&str> {
+    let mut split = Vec::new();
+    for component in components {
+        let mut depth = 0;
+        let mut start = 0;
+        for (i, c) in component.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                '/' if depth == 0 => {
+                    if i > start {
+                        split.push(&component[start..i]);
+                    }
+                    split.push("/");
+                    start = i + 1;
+                },
+                _ => {}
+            }
+        }
+        if start < component.len() {
+            split.push(&component[start..]);
+        }
+    }
+    return split;
+}
+
+// Splits on whitespace, keeping parenthesized groups like rgb(1, 2, 3) whole.
+pub(crate) fn split_components(value : &str) -> Vec<&str> {
+    let mut components = Vec::new();
+    let mut depth = 0;
+    let mut start = None;
+
+    for (i, c) in value.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+
+        if c.is_whitespace() && depth == 0 {
+            if let Some(s) = start.take() {
+                components.push(&value[s..i]);
+            }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+
+    if let Some(s) = start {
+        components.push(&value[s..]);
+    }
+
+    return components;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expanded(key : &str, value : &str) -> Vec<(String, String)> {
+        return expand_rule(&CssRule::new(key, value)).unwrap().into_iter()
+            .map(|rule| (rule.key, rule.value))
+            .collect();
+    }
+
+    fn pairs(longhands : &[(&str, &str)]) -> Vec<(String, String)> {
+        return longhands.iter().map(|&(key, value)| (key.to_string(), value.to_string())).collect();
+    }
+
+    #[test]
+    fn expands_box_shorthands() {
+        let sides = |key : &str, [top, right, bottom, left] : [&str; 4]| pairs(&[
+            (&format!("{}-top", key), top),
+            (&format!("{}-right", key), right),
+            (&format!("{}-bottom", key), bottom),
+            (&format!("{}-left", key), left),
+        ]);
+        assert_eq!(expanded("margin", "1px"), sides("margin", ["1px", "1px", "1px", "1px"]));
+        assert_eq!(expanded("margin", "1px 2px"), sides("margin", ["1px", "2px", "1px", "2px"]));
+        assert_eq!(expanded("padding", "1px 2px 3px"), sides("padding", ["1px", "2px", "3px", "2px"]));
+        assert_eq!(expanded("margin", "1px 2px calc(1px + 2px) 4px"), sides("margin", ["1px", "2px", "calc(1px + 2px)", "4px"]));
+        assert!(expand_rule(&CssRule::new("margin", "1px 2px 3px 4px 5px")).is_none());
+    }
+
+    #[test]
+    fn keeps_declaration_order() {
+        let mut root = ::parse_str("a { color: red; margin: 0 !important; z-index: 1 }").unwrap();
+        expand_shorthands(&mut root);
+        let keys: Vec<_> = root.rule_sets[0].rules.iter().map(|rule| rule.key.as_str()).collect();
+        assert_eq!(keys, vec!["color", "margin-top", "margin-right", "margin-bottom", "margin-left", "z-index"]);
+        assert!(root.rule_sets[0].rules[1..5].iter().all(|rule| rule.important));
+    }
+
+    #[test]
+    fn border_reads_components_in_any_order() {
+        let longhands = expanded("border", "black dashed 2px");
+        assert_eq!(longhands[0], ("border-top-width".to_string(), "2px".to_string()));
+        assert_eq!(longhands[4], ("border-top-style".to_string(), "dashed".to_string()));
+        assert_eq!(longhands[8], ("border-top-color".to_string(), "black".to_string()));
+
+        let partial = expanded("border", "solid");
+        assert_eq!(partial[0].1, "medium");
+        assert_eq!(partial[8].1, "currentcolor");
+
+        assert!(expand_rule(&CssRule::new("border", "solid solid")).is_none());
+        assert!(expand_rule(&CssRule::new("border", "1px bogus")).is_none());
+    }
+
+    #[test]
+    fn expands_font() {
+        let font = |style, weight, size, line_height, family| pairs(&[
+            ("font-style", style),
+            ("font-weight", weight),
+            ("font-size", size),
+            ("line-height", line_height),
+            ("font-family", family),
+        ]);
+        assert_eq!(expanded("font", "italic bold 12px/1.5 sans-serif"), font("italic", "bold", "12px", "1.5", "sans-serif"));
+        assert_eq!(expanded("font", "16px serif"), font("normal", "normal", "16px", "normal", "serif"));
+        assert_eq!(expanded("font", "bold 12px / 20px \"Times New Roman\", serif"),
+            font("normal", "bold", "12px", "20px", "\"Times New Roman\", serif"));
+        assert!(expand_rule(&CssRule::new("font", "12px")).is_none());
+        assert!(expand_rule(&CssRule::new("font", "caption")).is_none());
+    }
+
+    #[test]
+    fn border_sets_every_side() {
+        let longhands = expanded("border", "red 1px");
+        assert_eq!(longhands.len(), 12);
+        assert!(longhands.contains(&("border-top-width".to_string(), "1px".to_string())));
+        assert!(longhands.contains(&("border-left-style".to_string(), "none".to_string())));
+        assert!(longhands.contains(&("border-bottom-color".to_string(), "red".to_string())));
+    }
+
+    #[test]
+    fn border_overrides_earlier_longhands() {
+        let mut root = ::parse_str("a { border-color: blue; border-top-width: 4px; border: 1px solid red }").unwrap();
+        expand_shorthands(&mut root);
+        let rule_set = &root.rule_sets[0];
+        assert_eq!(rule_set.get("border-top-color"), Some("red"));
+        assert_eq!(rule_set.get("border-top-width"), Some("1px"));
+    }
+
+    #[test]
+    fn expands_media_and_nested_rule_sets() {
+        let mut root = ::parse_str("@media print { a { margin: 1px } } b { c { padding: 2px } }").unwrap();
+        expand_shorthands(&mut root);
+        assert_eq!(root.media_queries[0].rule_sets[0].get("margin-left"), Some("1px"));
+        assert_eq!(root.rule_sets[0].children[0].get("padding-top"), Some("2px"));
+        assert_eq!(root.rule_sets[0].children[0].get("padding"), None);
+    }
+
+    #[test]
+    fn border_style_expands_per_side() {
+        let longhands = expanded("border-style", "solid none");
+        assert_eq!(longhands[1], ("border-right-style".to_string(), "none".to_string()));
+    }
+
+    #[test]
+    fn background_reads_components_in_any_order() {
+        let background = |color, image, repeat, position, size| pairs(&[
+            ("background-color", color),
+            ("background-image", image),
+            ("background-repeat", repeat),
+            ("background-position", position),
+            ("background-size", size),
+        ]);
+        assert_eq!(expanded("background", "red"), background("red", "none", "repeat", "0% 0%", "auto"));
+        assert_eq!(expanded("background", "no-repeat center / cover url(a.png) #fff"),
+            background("#fff", "url(a.png)", "no-repeat", "center", "cover"));
+        assert_eq!(expanded("background", "left 10px top repeat-x linear-gradient(red, blue)"),
+            background("transparent", "linear-gradient(red, blue)", "repeat-x", "left 10px top", "auto"));
+        assert!(expand_rule(&CssRule::new("background", "red, blue")).is_none());
+        assert!(expand_rule(&CssRule::new("background", "center / red")).is_none());
+        assert!(expand_rule(&CssRule::new("background", "red blue")).is_none());
+    }
+
+    #[test]
+    fn inset_sets_the_sides() {
+        assert_eq!(expanded("inset", "1px 2px"), pairs(&[("top", "1px"), ("right", "2px"), ("bottom", "1px"), ("left", "2px")]));
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use image::{self, ImageError, ImageResult, RgbaImage};
+use image::error::{ParameterError, ParameterErrorKind};
+
+// The first glyph in an atlas; glyphs follow in ASCII order, left to right and top to
+// bottom, up to and including `~`.
+const FIRST_GLYPH: char = ' ';
+const LAST_GLYPH: char = '~';
+
+// A monospace bitmap font: an image holding a grid of equally sized glyph cells.
+#[derive(Debug)]
+pub struct FontAtlas {
+    image: RgbaImage,
+    glyph_width: u32,
+    glyph_height: u32,
+}
+impl FontAtlas {
+    // None unless the image holds at least one glyph cell, so texture coordinates are
+    // never divided by zero.
+    pub fn new(image : RgbaImage, glyph_width : u32, glyph_height : u32) -> Option<FontAtlas> {
+        if glyph_width == 0 || glyph_height == 0 || image.width() < glyph_width || image.height() < glyph_height {
+            return None;
+        }
+        return Some(FontAtlas {image, glyph_width, glyph_height});
+    }
+
+    pub fn open<P: AsRef<Path>>(path : P, glyph_width : u32, glyph_height : u32) -> ImageResult<FontAtlas> {
+        let image = image::open(path)?.to_rgba8();
+        return FontAtlas::new(image, glyph_width, glyph_height).ok_or_else(|| {
+            ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::DimensionMismatch))
+        });
+    }
+
+    // The pixels to upload as the texture sampled by the textured pipeline.
+    pub fn image(&self) -> &RgbaImage {
+        return &self.image;
+    }
+
+    pub fn glyph_size(&self) -> (u32, u32) {
+        return (self.glyph_width, self.glyph_height);
+    }
+
+    // The texture coordinates of the top-left and bottom-right corners of the glyph
+    // cell for `c`. Chars the atlas doesn't hold are drawn as `?`.
+    pub fn glyph_uv(&self, c : char) -> ([f32; 2], [f32; 2]) {
+        let c = if (FIRST_GLYPH..=LAST_GLYPH).contains(&c) { c } else { '?' };
+        let index = c as u32 - FIRST_GLYPH as u32;
+        let columns = self.image.width() / self.glyph_width;
+
+        let (width, height) = (self.image.width() as f32, self.image.height() as f32);
+        let left = (index % columns * self.glyph_width) as f32 / width;
+        let top = (index / columns * self.glyph_height) as f32 / height;
+        let right = left + self.glyph_width as f32 / width;
+        let bottom = top + self.glyph_height as f32 / height;
+        return ([left, top], [right, bottom]);
+    }
+}
+
+// CPU-side geometry for the textured pipeline, kept apart from the rect `Vertex`:
+// `uv` samples the font atlas and `color` tints the glyph.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TexturedVertex {
+    pub pos: [f32; 2],
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+}
+
+// The two triangles of one glyph with its top-left corner at the origin, as positions
+// and texture coordinates.
+type GlyphQuad = [([f32; 2], [f32; 2]); 6];
+
+// Two triangles per glyph, starting with the top-left corner of the first glyph at
+// (x, y). Spaces only advance the pen and newlines start a new line.
+pub fn text_vertices(atlas : &FontAtlas, text : &str, x : f32, y : f32, color : [f32; 4]) -> Vec<TexturedVertex> {
+    let size = (atlas.glyph_width as f32, atlas.glyph_height as f32);
+    return place_glyphs(text, (x, y), size, color, |c| glyph_quad(atlas, c, size));
+}
+
+// Keeps the quads of the glyphs drawn so far, keyed by char and size, so text that is
+// drawn every frame doesn't work out the same geometry again. Use one cache per atlas.
+#[derive(Debug, Default)]
+pub struct GlyphCache {
+    glyphs: HashMap<(char, u32), GlyphQuad>,
+    hits: usize,
+    misses: usize,
+}
+impl GlyphCache {
+    pub fn new() -> GlyphCache {
+        return GlyphCache::default();
+    }
+
+    // Like `text_vertices`, with the glyphs scaled to `size` pixels tall.
+    pub fn text_vertices(&mut self, atlas : &FontAtlas, text : &str, x : f32, y : f32, size : u32, color : [f32; 4])
+        -> Vec<TexturedVertex> {
+        let height = size as f32;
+        let width = atlas.glyph_width as f32 * height / atlas.glyph_height as f32;
+        let (glyphs, hits, misses) = (&mut self.glyphs, &mut self.hits, &mut self.misses);
+        return place_glyphs(text, (x, y), (width, height), color, |c| {
+            if let Some(quad) = glyphs.get(&(c, size)) {
+                *hits += 1;
+                return *quad;
+            }
+            *misses += 1;
+            return *glyphs.entry((c, size)).or_insert_with(|| glyph_quad(atlas, c, (width, height)));
+        });
+    }
+
+    // Glyphs that were found in the cache, and those that had to be added to it.
+    pub fn hits(&self) -> usize {
+        return self.hits;
+    }
+
+    pub fn misses(&self) -> usize {
+        return self.misses;
+    }
+}
+
+fn glyph_quad(atlas : &FontAtlas, c : char, (w, h) : (f32, f32)) -> GlyphQuad {
+    let ([u0, v0], [u1, v1]) = atlas.glyph_uv(c);
+    return [
+        ([0.0, 0.0], [u0, v0]),
+        ([w, 0.0], [u1, v0]),
+        ([w, h], [u1, v1]),
+        ([0.0, 0.0], [u0, v0]),
+        ([w, h], [u1, v1]),
+        ([0.0, h], [u0, v1]),
+    ];
+}
+
+// Moves the pen over `text`, placing the quad `glyph` gives for each char at it.
+fn place_glyphs<F: FnMut(char) -> GlyphQuad>(text : &str, (x, y) : (f32, f32), (w, h) : (f32, f32), color : [f32; 4],
+    mut glyph : F) -> Vec<TexturedVertex> {
+    let mut vertices = Vec::new();
+    let (mut pen_x, mut pen_y) = (x, y);
+
+    for c in text.chars() {
+        if c == '\n' {
+            pen_x = x;
+            pen_y += h;
+            continue;
+        }
+
+        if c != ' ' {
+            let vertex = |&([px, py], uv) : &([f32; 2], [f32; 2])| TexturedVertex {pos: [pen_x + px, pen_y + py], uv, color};
+            vertices.extend(glyph(c).iter().map(vertex));
+        }
+        pen_x += w;
+    }
+
+    return vertices;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_atlases_without_a_glyph_cell() {
+        assert!(FontAtlas::new(RgbaImage::new(80, 60), 0, 10).is_none());
+        assert!(FontAtlas::new(RgbaImage::new(80, 60), 8, 0).is_none());
+        assert!(FontAtlas::new(RgbaImage::new(4, 60), 8, 10).is_none());
+        assert!(FontAtlas::new(RgbaImage::new(80, 60), 8, 10).is_some());
+    }
+
+    #[test]
+    fn glyph_uvs_follow_the_grid() {
+        let atlas = FontAtlas::new(RgbaImage::new(80, 60), 8, 10).unwrap();
+        assert_eq!(atlas.glyph_uv(' '), ([0.0, 0.0], [0.1, 1.0 / 6.0]));
+        assert_eq!(atlas.glyph_uv('*').0, [0.0, 1.0 / 6.0]);
+        assert_eq!(atlas.glyph_uv('\u{e9}'), atlas.glyph_uv('?'));
+    }
+
+    #[test]
+    fn glyph_cache_reuses_quads() {
+        let atlas = FontAtlas::new(RgbaImage::new(80, 60), 8, 10).unwrap();
+        let mut cache = GlyphCache::new();
+        let vertices = cache.text_vertices(&atlas, "aa b", 0.0, 0.0, 20, [1.0; 4]);
+        assert_eq!(vertices.len(), 3 * 6);
+        assert_eq!(vertices[6].pos, [16.0, 0.0]);
+        assert_eq!((cache.hits(), cache.misses()), (1, 2));
+
+        // The same text again is drawn from the cache alone, one hit per glyph.
+        assert_eq!(cache.text_vertices(&atlas, "aa b", 0.0, 0.0, 20, [1.0; 4]), vertices);
+        assert_eq!((cache.hits(), cache.misses()), (1 + 3, 2));
+    }
+
+    #[test]
+    fn text_advances_the_pen() {
+        let atlas = FontAtlas::new(RgbaImage::new(80, 60), 8, 10).unwrap();
+        let color = [0.0, 0.0, 0.0, 1.0];
+        let vertices = text_vertices(&atlas, "a b\nc", 5.0, 5.0, color);
+        assert_eq!(vertices.len(), 3 * 6);
+        let corners: Vec<_> = vertices.chunks(6).map(|quad| quad[0].pos).collect();
+        assert_eq!(corners, vec![[5.0, 5.0], [21.0, 5.0], [5.0, 15.0]]);
+        assert_eq!(vertices[2].pos, [13.0, 15.0]);
+        assert_eq!((vertices[0].uv, vertices[2].uv), atlas.glyph_uv('a'));
+        assert!(vertices.iter().all(|vertex| vertex.color == color));
+    }
+}
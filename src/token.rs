@@ -0,0 +1,350 @@
+use parse::{normalize_newlines, unescape};
+
+// Tokens roughly as the CSS syntax spec defines them. Names and string contents are
+// unescaped, and punctuation such as `{`, `:` and `;` comes through as `Delim`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CssToken {
+    Ident(String),
+    Function(String),
+    AtKeyword(String),
+    Hash(String),
+    String(String),
+    Url(String),
+    Number(f32),
+    Percentage(f32),
+    Dimension(f32, String),
+    Whitespace,
+    Comment(String),
+    Delim(char),
+}
+
+// What the parser needs to know about a token; the text is only decoded by `tokenize`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum TokenKind {
+    Ident,
+    Function,
+    AtKeyword,
+    Hash,
+    String,
+    Url,
+    Number,
+    Percentage,
+    // The byte offset within the token where the unit starts.
+    Dimension(usize),
+    Whitespace,
+    Comment,
+    Delim(char),
+}
+
+#[derive(Debug)]
+pub(crate) struct SpannedToken {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+}
+impl SpannedToken {
+    // Strings, urls and comments that run into the end of the input have no closing delimiter.
+    pub fn is_unterminated(&self, text : &str) -> bool {
+        return match self.kind {
+            TokenKind::String => {
+                let quote = text.chars().next();
+                text.len() < 2 || text.chars().last() != quote || ends_with_escape(&text[..text.len() - 1])
+            },
+            TokenKind::Url => !text.ends_with(')') || ends_with_escape(&text[..text.len() - 1]),
+            TokenKind::Comment => text.len() < 4 || !text.ends_with("*/"),
+            _ => false
+        }
+    }
+
+    fn token(&self, input : &str) -> CssToken {
+        let text = &input[self.start..self.end];
+        let number = |text : &str| text.parse::<f32>().unwrap_or(0.0);
+
+        return match self.kind {
+            TokenKind::Ident => CssToken::Ident(unescape(text)),
+            TokenKind::Function => CssToken::Function(unescape(&text[..text.len() - 1])),
+            TokenKind::AtKeyword => CssToken::AtKeyword(unescape(&text[1..])),
+            TokenKind::Hash => CssToken::Hash(unescape(&text[1..])),
+            TokenKind::String if self.is_unterminated(text) => CssToken::String(unescape(&text[1..])),
+            TokenKind::String => CssToken::String(unescape(&text[1..text.len() - 1])),
+            TokenKind::Url => {
+                let url = text[4..].strip_suffix(')').unwrap_or(&text[4..]);
+                CssToken::Url(unescape(url.trim()))
+            },
+            TokenKind::Number => CssToken::Number(number(text)),
+            TokenKind::Percentage => CssToken::Percentage(number(&text[..text.len() - 1])),
+            TokenKind::Dimension(unit) => CssToken::Dimension(number(&text[..unit]), unescape(&text[unit..])),
+            TokenKind::Whitespace => CssToken::Whitespace,
+            TokenKind::Comment if self.is_unterminated(text) => CssToken::Comment(text[2..].to_string()),
+            TokenKind::Comment => CssToken::Comment(text[2..text.len() - 2].to_string()),
+            TokenKind::Delim(c) => CssToken::Delim(c)
+        }
+    }
+}
+
+pub fn tokenize(input : &str) -> Vec<CssToken> {
+    let input = normalize_newlines(input);
+    return spanned_tokens(&input)
+        .map(|spanned| spanned.token(&input))
+        .collect();
+}
+
+// The tokens of `input` with their byte ranges, read one at a time as they are asked for.
+pub(crate) fn spanned_tokens(input : &str) -> Tokenizer<'_> {
+    return Tokenizer {input, pos: 0};
+}
+
+fn ends_with_escape(text : &str) -> bool {
+    return text.chars().rev().take_while(|&c| c == '\\').count() % 2 == 1;
+}
+
+fn is_name_start(c : char) -> bool {
+    return c.is_alphabetic() || c == '_' || !c.is_ascii();
+}
+
+fn is_name_char(c : char) -> bool {
+    return is_name_start(c) || c.is_ascii_digit() || c == '-';
+}
+
+pub(crate) struct Tokenizer<'a> {
+    input: &'a str,
+    pos: usize,
+}
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = SpannedToken;
+
+    fn next(&mut self) -> Option<SpannedToken> {
+        if self.pos >= self.input.len() {
+            return None;
+        }
+        let start = self.pos;
+        let kind = self.next_token();
+        return Some(SpannedToken {kind, start, end: self.pos});
+    }
+}
+impl<'a> Tokenizer<'a> {
+    fn peek(&self, ahead : usize) -> Option<char> {
+        return self.input[self.pos..].chars().nth(ahead);
+    }
+
+    fn bump(&mut self) {
+        if let Some(c) = self.peek(0) {
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn next_token(&mut self) -> TokenKind {
+        let c = self.peek(0).unwrap();
+
+        if c.is_whitespace() {
+            while self.peek(0).is_some_and(char::is_whitespace) {
+                self.bump();
+            }
+            return TokenKind::Whitespace;
+        }
+
+        if c == '/' && self.peek(1) == Some('*') {
+            return self.consume_comment();
+        }
+
+        if c == '"' || c == '\'' {
+            return self.consume_string(c);
+        }
+
+        if self.starts_number() {
+            return self.consume_numeric();
+        }
+
+        if c == '@' && self.starts_ident(1) {
+            self.bump();
+            self.consume_name();
+            return TokenKind::AtKeyword;
+        }
+
+        if c == '#' && (self.peek(1).is_some_and(is_name_char) || self.starts_escape(1)) {
+            self.bump();
+            self.consume_name();
+            return TokenKind::Hash;
+        }
+
+        if self.starts_ident(0) {
+            return self.consume_ident_like();
+        }
+
+        self.bump();
+        return TokenKind::Delim(c);
+    }
+
+    fn consume_comment(&mut self) -> TokenKind {
+        self.pos += 2;
+        self.pos = match self.input[self.pos..].find("*/") {
+            Some(end) => self.pos + end + 2,
+            None => self.input.len()
+        };
+        return TokenKind::Comment;
+    }
+
+    // Runs to the matching quote, or to the end of the input if there is none.
+    fn consume_string(&mut self, quote : char) -> TokenKind {
+        self.bump();
+        while let Some(c) = self.peek(0) {
+            self.bump();
+            if c == quote {
+                break;
+            }
+            if c == '\\' {
+                self.bump();
+            }
+        }
+        return TokenKind::String;
+    }
+
+    fn starts_escape(&self, ahead : usize) -> bool {
+        return self.peek(ahead) == Some('\\') && self.peek(ahead + 1).is_some_and(|c| c != '\n');
+    }
+
+    fn starts_ident(&self, ahead : usize) -> bool {
+        return match self.peek(ahead) {
+            Some('-') => self.peek(ahead + 1).is_some_and(|c| is_name_start(c) || c == '-')
+                || self.starts_escape(ahead + 1),
+            Some('\\') => self.starts_escape(ahead),
+            Some(c) => is_name_start(c),
+            None => false
+        }
+    }
+
+    fn starts_number(&self) -> bool {
+        let digit = |ahead| self.peek(ahead).is_some_and(|c : char| c.is_ascii_digit());
+        return match self.peek(0) {
+            Some('+') | Some('-') => digit(1) || (self.peek(1) == Some('.') && digit(2)),
+            Some('.') => digit(1),
+            Some(c) => c.is_ascii_digit(),
+            None => false
+        }
+    }
+
+    fn consume_name(&mut self) {
+        loop {
+            if self.peek(0).is_some_and(is_name_char) {
+                self.bump();
+            } else if self.starts_escape(0) {
+                self.consume_escape();
+            } else {
+                break;
+            }
+        }
+    }
+
+    // A backslash and either one char, or up to six hex digits and a whitespace.
+    fn consume_escape(&mut self) {
+        self.bump();
+        let mut digits = 0;
+        while digits < 6 && self.peek(0).is_some_and(|c| c.is_ascii_hexdigit()) {
+            self.bump();
+            digits += 1;
+        }
+
+        if digits == 0 || self.peek(0).is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    fn consume_digits(&mut self) {
+        while self.peek(0).is_some_and(|c| c.is_ascii_digit()) {
+            self.bump();
+        }
+    }
+
+    fn consume_numeric(&mut self) -> TokenKind {
+        let start = self.pos;
+        if let Some('+') | Some('-') = self.peek(0) {
+            self.bump();
+        }
+        self.consume_digits();
+        if self.peek(0) == Some('.') && self.peek(1).is_some_and(|c| c.is_ascii_digit()) {
+            self.bump();
+            self.consume_digits();
+        }
+
+        let exponent = match (self.peek(1), self.peek(2)) {
+            (Some('+'), Some(c)) | (Some('-'), Some(c)) => c.is_ascii_digit(),
+            (Some(c), _) => c.is_ascii_digit(),
+            _ => false
+        };
+        if (self.peek(0) == Some('e') || self.peek(0) == Some('E')) && exponent {
+            self.bump();
+            self.bump();
+            self.consume_digits();
+        }
+
+        if self.peek(0) == Some('%') {
+            self.bump();
+            return TokenKind::Percentage;
+        }
+        if self.starts_ident(0) {
+            let unit = self.pos - start;
+            self.consume_name();
+            return TokenKind::Dimension(unit);
+        }
+        return TokenKind::Number;
+    }
+
+    // An unquoted `url(...)` is one token, so that comments and quotes inside it are kept.
+    fn consume_ident_like(&mut self) -> TokenKind {
+        let start = self.pos;
+        self.consume_name();
+        if self.peek(0) != Some('(') {
+            return TokenKind::Ident;
+        }
+        let is_url = unescape(&self.input[start..self.pos]).eq_ignore_ascii_case("url");
+        self.bump();
+
+        let rest = self.input[self.pos..].trim_start();
+        if !is_url || rest.starts_with('"') || rest.starts_with('\'') {
+            return TokenKind::Function;
+        }
+
+        while let Some(c) = self.peek(0) {
+            if c == ')' {
+                self.bump();
+                break;
+            }
+            if self.starts_escape(0) {
+                self.consume_escape();
+            } else {
+                self.bump();
+            }
+        }
+        return TokenKind::Url;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use super::CssToken::*;
+
+    fn text(s : &str) -> ::std::string::String {
+        return s.to_string();
+    }
+
+    #[test]
+    fn tokenizes_a_rule_set() {
+        assert_eq!(tokenize("a { color: #fff; width: 10px; } /* c */"), vec![
+            Ident(text("a")), Whitespace, Delim('{'), Whitespace, Ident(text("color")), Delim(':'), Whitespace,
+            Hash(text("fff")), Delim(';'), Whitespace, Ident(text("width")), Delim(':'), Whitespace,
+            Dimension(10.0, text("px")), Delim(';'), Whitespace, Delim('}'), Whitespace, Comment(text(" c ")),
+        ]);
+    }
+
+    #[test]
+    fn tokenizes_urls_numbers_and_escapes() {
+        assert_eq!(tokenize("@media url(a/*b*/.png) url( \"x\" ) 50% -1.5e2 \"q\\\"r\" --x \\41 b"), vec![
+            AtKeyword(text("media")), Whitespace, Url(text("a/*b*/.png")), Whitespace, Function(text("url")), Whitespace,
+            String(text("x")), Whitespace, Delim(')'), Whitespace, Percentage(50.0), Whitespace, Number(-150.0), Whitespace,
+            String(text("q\"r")), Whitespace, Ident(text("--x")), Whitespace, Ident(text("Ab")),
+        ]);
+        assert_eq!(tokenize("rgb(1,2)"), vec![Function(text("rgb")), Number(1.0), Delim(','), Number(2.0), Delim(')')]);
+    }
+}
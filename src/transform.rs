@@ -0,0 +1,124 @@
+use cgmath::{Deg, Matrix4, SquareMatrix, Vector3};
+
+use gradient::parse_angle;
+use length::{Length, parse_length};
+use value::split_value_list;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// One function of a `transform` list. Translations are in pixels and rotations in
+// degrees clockwise, as on screen with y pointing down.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Transform {
+    Translate(f32, f32),
+    Scale(f32, f32),
+    Rotate(f32),
+}
+impl Transform {
+    pub fn matrix(&self) -> Matrix4<f32> {
+        return match *self {
+            Transform::Translate(x, y) => Matrix4::from_translation(Vector3::new(x, y, 0.0)),
+            Transform::Scale(x, y) => Matrix4::from_nonuniform_scale(x, y, 1.0),
+            Transform::Rotate(degrees) => Matrix4::from_angle_z(Deg(degrees)),
+        }
+    }
+}
+
+// The model matrix for `transforms` applied about `origin`, the center of the box as
+// with the default `transform-origin`. Like in CSS, the last function in the list is
+// the first applied to each point. Multiply it into the projection to get the MVP.
+pub fn transform_matrix(transforms : &[Transform], origin : [f32; 2]) -> Matrix4<f32> {
+    let to_origin = Matrix4::from_translation(Vector3::new(origin[0], origin[1], 0.0));
+    let from_origin = Matrix4::from_translation(Vector3::new(-origin[0], -origin[1], 0.0));
+    let combined = transforms.iter().fold(Matrix4::identity(), |matrix, transform| matrix * transform.matrix());
+    return to_origin * combined * from_origin;
+}
+
+// Reads `translate(10px, 20px) rotate(45deg)` and the like, with `none` as no functions.
+// Translations must be in pixels, as percentages would need the size of the box.
+pub fn parse_transform(value : &str) -> Option<Vec<Transform>> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("none") {
+        return Some(Vec::new());
+    }
+
+    let mut transforms = Vec::new();
+    let mut rest = value;
+    while !rest.is_empty() {
+        let open = rest.find('(')?;
+        let close = open + rest[open..].find(')')?;
+        let name = rest[..open].trim().to_ascii_lowercase();
+        let args = split_value_list(&rest[open + 1..close]);
+        transforms.push(function(&name, &args)?);
+        rest = rest[close + 1..].trim_start();
+    }
+
+    if transforms.is_empty() {
+        return None;
+    }
+    return Some(transforms);
+}
+
+fn function(name : &str, args : &[String]) -> Option<Transform> {
+    let arg = |i : usize| args.get(i).map(|arg| arg.as_str());
+    let count = |min : usize, max : usize| args.len() >= min && args.len() <= max;
+
+    return match name {
+        "translate" if count(1, 2) => Some(Transform::Translate(px(arg(0)?)?, arg(1).map_or(Some(0.0), px)?)),
+        "translatex" if count(1, 1) => Some(Transform::Translate(px(arg(0)?)?, 0.0)),
+        "translatey" if count(1, 1) => Some(Transform::Translate(0.0, px(arg(0)?)?)),
+        "scale" if count(1, 2) => {
+            let x = number(arg(0)?)?;
+            Some(Transform::Scale(x, arg(1).map_or(Some(x), number)?))
+        },
+        "scalex" if count(1, 1) => Some(Transform::Scale(number(arg(0)?)?, 1.0)),
+        "scaley" if count(1, 1) => Some(Transform::Scale(1.0, number(arg(0)?)?)),
+        "rotate" if count(1, 1) => Some(Transform::Rotate(parse_angle(&arg(0)?.to_ascii_lowercase())?)),
+        _ => None
+    }
+}
+
+fn px(arg : &str) -> Option<f32> {
+    return match parse_length(arg)? {
+        Length::Px(value) => Some(value),
+        _ => None
+    }
+}
+
+fn number(arg : &str) -> Option<f32> {
+    return arg.parse().ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::Vector4;
+
+    #[test]
+    fn parses_transform_lists() {
+        assert_eq!(parse_transform("translate(10px,20px) rotate(45deg)"), Some(vec![Transform::Translate(10.0, 20.0), Transform::Rotate(45.0)]));
+        assert_eq!(parse_transform("scale(2) translateX(5px) scaleY(3) rotate(0.25turn)"), Some(vec![
+            Transform::Scale(2.0, 2.0),
+            Transform::Translate(5.0, 0.0),
+            Transform::Scale(1.0, 3.0),
+            Transform::Rotate(90.0),
+        ]));
+        assert_eq!(parse_transform("none"), Some(vec![]));
+        for malformed in ["", "translate(10%)", "skew(10deg)", "rotate(45)", "translate(1px, 2px, 3px)", "translate(1px"].iter() {
+            assert_eq!(parse_transform(malformed), None, "{}", malformed);
+        }
+    }
+
+    #[test]
+    fn applies_transforms_in_order() {
+        let translate = transform_matrix(&[Transform::Translate(10.0, 20.0)], [50.0, 50.0]);
+        assert_eq!(translate, Matrix4::from_translation(Vector3::new(10.0, 20.0, 0.0)));
+
+        let matrix = transform_matrix(&[Transform::Translate(10.0, 0.0), Transform::Rotate(90.0)], [0.0, 0.0]);
+        let point = matrix * Vector4::new(1.0, 0.0, 0.0, 1.0);
+        assert!((point.x - 10.0).abs() < 1e-5 && (point.y - 1.0).abs() < 1e-5, "{:?}", point);
+    }
+}
@@ -0,0 +1,215 @@
+use calc::{CalcExpr, parse_calc};
+use color::{Color, named_color, parse_color};
+use content::{ContentValue, parse_content};
+use gradient::{Gradient, parse_gradient};
+use length::{Length, parse_length};
+use parse::{unescape, unprefixed};
+use shadow::{BoxShadow, parse_box_shadow};
+use transform::{Transform, parse_transform};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+// Properties whose unitless values are plain numbers rather than zero lengths.
+const NUMBER_PROPERTIES: &[&str] = &[
+    "opacity", "z-index", "flex-grow", "flex-shrink", "order", "line-height", "font-weight",
+    "orphans", "widows", "column-count", "fill-opacity", "stroke-opacity",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum CssValue {
+    Number(f32),
+    Percentage(f32),
+    Length(Length),
+    Calc(CalcExpr),
+    Color(Color),
+    Gradient(Gradient),
+    Transform(Vec<Transform>),
+    BoxShadow(Vec<BoxShadow>),
+    Content(Vec<ContentValue>),
+    Keyword(String),
+    QuotedString(String),
+    // Anything that is not a single typed component, like `1px solid red`.
+    Other(String),
+}
+impl Default for CssValue {
+    fn default() -> CssValue {
+        return CssValue::Other("".to_string());
+    }
+}
+
+pub fn parse_value(property : &str, raw : &str) -> CssValue {
+    let property = unprefixed(property);
+    let raw = raw.trim();
+
+    if property == "content" {
+        if let Some(content) = parse_content(raw) {
+            return CssValue::Content(content);
+        }
+    }
+
+    if let Some(content) = quoted_content(raw) {
+        return CssValue::QuotedString(unescape(content));
+    }
+
+    if let Some(percent) = raw.strip_suffix('%').and_then(|n| n.parse::<f32>().ok()) {
+        return CssValue::Percentage(percent);
+    }
+
+    if let Ok(number) = raw.parse::<f32>() {
+        let is_length = number == 0.0 && !NUMBER_PROPERTIES.contains(&property);
+        return if is_length { CssValue::Length(Length::Px(0.0)) } else { CssValue::Number(number) };
+    }
+
+    if let Some(length) = parse_length(raw) {
+        return CssValue::Length(length);
+    }
+
+    if let Some(calc) = parse_calc(raw) {
+        return CssValue::Calc(calc);
+    }
+
+    // Bare words are only colors where a color is expected, so `animation-name: red` stays a keyword.
+    let is_keyword = !raw.is_empty() && raw.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    if is_keyword && !(takes_color(property) && named_color(raw).is_some()) {
+        return CssValue::Keyword(raw.to_string());
+    }
+
+    if let Some(color) = parse_color(raw) {
+        return CssValue::Color(color);
+    }
+
+    if let Some(gradient) = parse_gradient(raw) {
+        return CssValue::Gradient(gradient);
+    }
+
+    if property == "transform" {
+        if let Some(transforms) = parse_transform(raw) {
+            return CssValue::Transform(transforms);
+        }
+    }
+
+    if property == "box-shadow" {
+        if let Some(shadows) = parse_box_shadow(raw) {
+            return CssValue::BoxShadow(shadows);
+        }
+    }
+
+    return CssValue::Other(raw.to_string());
+}
+
+fn takes_color(property : &str) -> bool {
+    return property.ends_with("color")
+        || matches!(property, "background" | "border" | "outline" | "fill" | "stroke");
+}
+
+fn quoted_content(raw : &str) -> Option<&str> {
+    return quote(raw).map(|_| &raw[1..raw.len() - 1]);
+}
+
+// The quote character when the whole of `raw` is one quoted string, so `"a", "b"`,
+// which closes its first string early, has none.
+pub(crate) fn quote(raw : &str) -> Option<char> {
+    let first = raw.chars().next()?;
+    if (first != '"' && first != '\'') || raw.len() < 2 {
+        return None;
+    }
+
+    let mut escaped = false;
+    for (i, c) in raw.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            x if x == first => return if i == raw.len() - 1 { Some(first) } else { None },
+            _ => {}
+        }
+    }
+    return None;
+}
+
+// Splits on top-level commas, so `rgb(1, 2, 3)` and `"a, b"` stay in one item.
+pub fn split_value_list(raw : &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut item = String::new();
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+
+    for c in raw.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            x if Some(x) == quote => quote = None,
+            _ if quote.is_some() => {},
+            '"' | '\'' => quote = Some(c),
+            '(' => depth += 1,
+            ')' if depth > 0 => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(item.trim().to_string());
+                item.clear();
+                continue;
+            },
+            _ => {}
+        }
+        item.push(c);
+    }
+
+    if !item.trim().is_empty() || !items.is_empty() {
+        items.push(item.trim().to_string());
+    }
+    return items;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_percentages_and_lengths() {
+        assert_eq!(parse_value("opacity", "0.5"), CssValue::Number(0.5));
+        assert_eq!(parse_value("opacity", "0"), CssValue::Number(0.0));
+        assert_eq!(parse_value("width", "50%"), CssValue::Percentage(50.0));
+        assert_eq!(parse_value("width", "10px"), CssValue::Length(Length::Px(10.0)));
+        assert_eq!(parse_value("margin", "0"), CssValue::Length(Length::Px(0.0)));
+    }
+
+    #[test]
+    fn colors_only_where_expected() {
+        assert_eq!(parse_value("color", "red"), CssValue::Color(Color::new(255, 0, 0, 255)));
+        assert_eq!(parse_value("animation-name", "red"), CssValue::Keyword("red".to_string()));
+        assert_eq!(parse_value("background", "#fff"), CssValue::Color(Color::new(255, 255, 255, 255)));
+        assert_eq!(parse_value("border", "1px solid red"), CssValue::Other("1px solid red".to_string()));
+    }
+
+    #[test]
+    fn rules_keep_their_parsed_value() {
+        let root = ::parse_str("a { width: 5px }").unwrap();
+        assert_eq!(root.rule_sets[0].rules[0].parsed_value(), &CssValue::Length(Length::Px(5.0)));
+    }
+
+    #[test]
+    fn quoted_strings() {
+        assert_eq!(parse_value("font-family", "\"Helvetica Neue\""), CssValue::QuotedString("Helvetica Neue".to_string()));
+        assert_eq!(parse_value("quotes", "'a\\'b'"), CssValue::QuotedString("a'b".to_string()));
+    }
+
+    #[test]
+    fn quoted_lists_are_not_one_string() {
+        let raw = "\"Helvetica Neue\", \"Arial\"";
+        assert_eq!(parse_value("font-family", raw), CssValue::Other(raw.to_string()));
+        assert_eq!(quote("'a' 'b'"), None);
+        assert_eq!(quote("\"a\\\"\""), Some('"'));
+    }
+
+    #[test]
+    fn value_lists_keep_quoted_commas() {
+        assert_eq!(split_value_list("Arial, \"Helvetica Neue\", sans-serif"), vec!["Arial", "\"Helvetica Neue\"", "sans-serif"]);
+        assert_eq!(split_value_list("\"a, b\", rgb(1, 2, 3)"), vec!["\"a, b\"", "rgb(1, 2, 3)"]);
+        assert!(split_value_list("").is_empty());
+
+        let root = ::parse_str("a { font-family: Arial, sans-serif; }").unwrap();
+        assert_eq!(root.rule_sets[0].rules[0].values(), vec!["Arial", "sans-serif"]);
+    }
+}
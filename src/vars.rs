@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use parse::{CssRoot, CssRuleSet};
+
+// Guards against custom properties that reference each other in a cycle.
+const MAX_VAR_DEPTH: usize = 16;
+
+// Substitutes var(--name) and var(--name, fallback) in every value. Custom properties
+// declared in the same rule set win over those declared on `:root`. References that
+// cannot be resolved and have no fallback are left untouched.
+pub fn resolve_vars(root : &mut CssRoot) {
+    let mut globals = HashMap::new();
+    for rule_set in root.rule_sets.iter().filter(|r| r.selectors.iter().any(|s| s == ":root")) {
+        globals.extend(custom_properties(rule_set));
+    }
+
+    let media_rule_sets = root.media_queries.iter_mut().flat_map(|m| m.rule_sets.iter_mut());
+    for rule_set in root.rule_sets.iter_mut().chain(media_rule_sets) {
+        let mut vars = globals.clone();
+        vars.extend(custom_properties(rule_set));
+
+        for rule in rule_set.rules.iter_mut() {
+            let value = substitute(&rule.value, &vars, 0);
+            rule.set_value(&value);
+        }
+    }
+}
+
+fn custom_properties(rule_set : &CssRuleSet) -> HashMap<String, String> {
+    return rule_set.rules.iter()
+        .filter(|rule| rule.key.starts_with("--"))
+        .map(|rule| (rule.key.clone(), rule.value.clone()))
+        .collect();
+}
+
+fn substitute(value : &str, vars : &HashMap<String, String>, depth : usize) -> String {
+    if depth > MAX_VAR_DEPTH {
+        return value.to_string();
+    }
+
+    let mut result = String::new();
+    let mut rest = value;
+
+    while let Some(start) = rest.find("var(") {
+        let args_start = start + 4;
+        let args_end = match matching_paren(&rest[args_start..]) {
+            Some(end) => args_start + end,
+            None => break
+        };
+
+        let args = &rest[args_start..args_end];
+        let (name, fallback) = match top_level_comma(args) {
+            Some(comma) => (args[..comma].trim(), Some(args[comma + 1..].trim())),
+            None => (args.trim(), None)
+        };
+
+        result.push_str(&rest[..start]);
+        match (vars.get(name), fallback) {
+            (Some(var), _) => result.push_str(&substitute(var, vars, depth + 1)),
+            (None, Some(fallback)) => result.push_str(&substitute(fallback, vars, depth + 1)),
+            (None, None) => result.push_str(&rest[start..args_end + 1])
+        }
+        rest = &rest[args_end + 1..];
+    }
+
+    result.push_str(rest);
+    return result;
+}
+
+// Index of the `)` closing a group whose `(` is just before `s`.
+fn matching_paren(s : &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    return None;
+}
+
+fn top_level_comma(s : &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => return Some(i),
+            _ => {}
+        }
+    }
+    return None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolved(css : &str) -> CssRoot {
+        let mut root = ::parse_str(css).unwrap();
+        resolve_vars(&mut root);
+        return root;
+    }
+
+    #[test]
+    fn substitutes_custom_properties() {
+        let root = resolved(":root { --main: blue; --pad: 2px } a { --main-color: red; color: var(--main-color); \
+            c: var(--main); e: calc(var(--pad) * 2) var(--pad) }");
+        let rule_set = &root.rule_sets[1];
+        assert_eq!(rule_set.get("color"), Some("red"));
+        assert_eq!(rule_set.get("c"), Some("blue"));
+        assert_eq!(rule_set.get("e"), Some("calc(2px * 2) 2px"));
+    }
+
+    #[test]
+    fn falls_back_when_undefined() {
+        let root = resolved(":root { --main: blue } a { b: var(--nope, green); d: var(--nope); f: var(--nope, var(--main)) }");
+        let rule_set = &root.rule_sets[1];
+        assert_eq!(rule_set.get("b"), Some("green"));
+        assert_eq!(rule_set.get("d"), Some("var(--nope)"));
+        assert_eq!(rule_set.get("f"), Some("blue"));
+    }
+
+    #[test]
+    fn resolves_inside_media_queries() {
+        let root = resolved(":root { --main: blue } @media print { m { color: var(--main) } }");
+        assert_eq!(root.media_queries[0].rule_sets[0].get("color"), Some("blue"));
+    }
+
+    #[test]
+    fn stops_at_cycles() {
+        let root = resolved("p { --a: var(--b); --b: var(--a); x: var(--a) }");
+        assert!(root.rule_sets[0].get("x").unwrap().starts_with("var(--"));
+    }
+}
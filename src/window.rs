@@ -0,0 +1,257 @@
+use std::time::{Duration, Instant};
+
+use cgmath::{Matrix4, SquareMatrix};
+
+use layout::{LayoutBox, layout};
+use paint::{Vertex, ortho_for, paint_boxes, paint_hovered};
+use parse::CssRoot;
+use transform::{Transform, transform_matrix};
+
+// How fast the spin demo turns the boxes, in degrees per second clockwise.
+pub const SPIN_DEGREES_PER_SECOND: f32 = 90.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowOptions {
+    pub width: u32,
+    pub height: u32,
+    pub title: String,
+    pub vsync: bool,
+    pub debug_fps: bool,
+    // Spins the boxes, see `spin_matrix`. The window itself stays where it is either way.
+    pub demo: bool,
+}
+impl Default for WindowOptions {
+    fn default() -> WindowOptions {
+        return WindowOptions {
+            width: 512,
+            height: 512,
+            title: "painter".to_string(),
+            vsync: true,
+            debug_fps: false,
+            demo: false,
+        }
+    }
+}
+impl WindowOptions {
+    // The defaults, with `--demo` turning on the demo. Other arguments are left for
+    // the caller.
+    pub fn from_args<I: IntoIterator<Item = String>>(args : I) -> WindowOptions {
+        let demo = args.into_iter().any(|arg| arg == "--demo");
+        return WindowOptions {demo, ..WindowOptions::default()};
+    }
+
+    pub fn projection(&self) -> Matrix4<f32> {
+        return ortho_for(self.width, self.height);
+    }
+
+    // The model matrix for the frame `elapsed` seconds in: the spin about the middle of
+    // the window in the demo, and none otherwise.
+    pub fn model_matrix(&self, elapsed : f32) -> Matrix4<f32> {
+        if !self.demo {
+            return Matrix4::identity();
+        }
+        return spin_matrix(elapsed, [self.width as f32 / 2.0, self.height as f32 / 2.0]);
+    }
+}
+
+// Measures frame durations for the render loop. With `debug_fps` set, the average
+// frame rate is printed to stderr once per second.
+#[derive(Debug)]
+pub struct FrameTimer {
+    debug_fps: bool,
+    last_frame: Instant,
+    accumulated: Duration,
+    frames: u32,
+    fps: f32,
+}
+impl FrameTimer {
+    pub fn new(debug_fps : bool) -> FrameTimer {
+        return FrameTimer {
+            debug_fps,
+            last_frame: Instant::now(),
+            accumulated: Duration::new(0, 0),
+            frames: 0,
+            fps: 0.0,
+        }
+    }
+
+    // Call once per frame; returns the seconds since the previous call.
+    pub fn tick(&mut self) -> f32 {
+        let now = Instant::now();
+        let delta = now - self.last_frame;
+        self.last_frame = now;
+
+        self.accumulated += delta;
+        self.frames += 1;
+        if self.accumulated >= Duration::from_secs(1) {
+            self.fps = self.frames as f32 / self.accumulated.as_secs_f32();
+            if self.debug_fps {
+                eprintln!("{:.1} fps", self.fps);
+            }
+            self.accumulated = Duration::new(0, 0);
+            self.frames = 0;
+        }
+
+        return delta.as_secs_f32();
+    }
+
+    // The average frame rate over the last full second.
+    pub fn fps(&self) -> f32 {
+        return self.fps;
+    }
+}
+
+// The model matrix of the spin demo after `elapsed` seconds, turning about `origin`.
+// Sum the deltas from `FrameTimer::tick` and multiply the result into the projection
+// to get each frame's `u_ModelViewProj`.
+pub fn spin_matrix(elapsed : f32, origin : [f32; 2]) -> Matrix4<f32> {
+    let degrees = (elapsed * SPIN_DEGREES_PER_SECOND) % 360.0;
+    return transform_matrix(&[Transform::Rotate(degrees)], origin);
+}
+
+// What the render loop knows about the scene between frames: the laid out boxes,
+// the projection for the window size and the last cursor position reported by the window.
+#[derive(Debug)]
+pub struct RenderState {
+    pub boxes: Vec<LayoutBox>,
+    pub cursor: Option<(f32, f32)>,
+    pub projection: Matrix4<f32>,
+    // Kept to lay the boxes out again when the window is resized.
+    root: Option<CssRoot>,
+    viewport: (f32, f32),
+}
+impl Default for RenderState {
+    fn default() -> RenderState {
+        return RenderState::new(Vec::new());
+    }
+}
+impl RenderState {
+    // Boxes laid out elsewhere stay as they are on resize, and are drawn untransformed
+    // until then.
+    pub fn new(boxes : Vec<LayoutBox>) -> RenderState {
+        return RenderState {boxes, cursor: None, projection: Matrix4::identity(), root: None, viewport: (0.0, 0.0)};
+    }
+
+    // Lays `root` out for a window of the given size, and again on every `on_resize`.
+    pub fn with_root(root : CssRoot, width : u32, height : u32) -> RenderState {
+        let mut state = RenderState::new(Vec::new());
+        state.root = Some(root);
+        state.on_resize(width, height);
+        return state;
+    }
+
+    // Call on `Resized`, after updating the render target views, so percentages and
+    // positions follow the new viewport.
+    pub fn on_resize(&mut self, width : u32, height : u32) {
+        self.projection = ortho_for(width, height);
+        self.viewport = (width as f32, height as f32);
+        if let Some(ref root) = self.root {
+            self.boxes = layout(root, self.viewport);
+        }
+    }
+
+    // The vertices for the next frame, with the hovered box in its `:hover` style. They
+    // come from the same layout `hit_test` uses, so the box lights up where it is drawn.
+    pub fn paint(&self) -> Vec<Vertex> {
+        return match self.root {
+            Some(ref root) => paint_hovered(root, self.viewport, self.hovered_selectors()),
+            None => paint_boxes(&self.boxes)
+        }
+    }
+
+    // Returns whether the hovered box changed, meaning its vertices need repainting.
+    pub fn mouse_moved(&mut self, x : f32, y : f32) -> bool {
+        let before = self.hovered();
+        self.cursor = Some((x, y));
+        return self.hovered() != before;
+    }
+
+    // The index of the topmost box whose border box contains the point. Later boxes
+    // are painted over earlier ones, so they are tested first.
+    pub fn hit_test(&self, x : f32, y : f32) -> Option<usize> {
+        return self.boxes.iter().rposition(|layout_box| layout_box.border.contains(x, y));
+    }
+
+    pub fn hovered(&self) -> Option<usize> {
+        let (x, y) = self.cursor?;
+        return self.hit_test(x, y);
+    }
+
+    // The selectors of the hovered box, as `paint_hovered` takes them.
+    pub fn hovered_selectors(&self) -> Option<&[String]> {
+        return self.hovered().map(|i| &self.boxes[i].selectors[..]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Vector4;
+
+    #[test]
+    fn demo_is_opt_in() {
+        assert!(!WindowOptions::from_args(vec!["painter".to_string()]).demo);
+        assert!(WindowOptions::from_args(vec!["painter".to_string(), "--demo".to_string()]).demo);
+    }
+
+    #[test]
+    fn spins_only_in_the_demo() {
+        let still = WindowOptions::from_args(vec!["painter".to_string()]);
+        assert_eq!(still.model_matrix(1.0), Matrix4::identity());
+
+        let demo = WindowOptions::from_args(vec!["painter".to_string(), "--demo".to_string()]);
+        assert_eq!(demo.model_matrix(1.0), spin_matrix(1.0, [256.0, 256.0]));
+    }
+
+    #[test]
+    fn hovers_the_box_where_it_is_painted() {
+        let root = ::parse_str("a { left: 10px; top: 10px; width: 100px; height: 100px; margin: 20px; \
+            background-color: red } a:hover { background-color: blue }").unwrap();
+        let mut state = RenderState::with_root(root, 200, 200);
+
+        assert!(!state.mouse_moved(15.0, 15.0));
+        assert_eq!(state.paint()[0].color, [1.0, 0.0, 0.0, 1.0]);
+
+        assert!(state.mouse_moved(35.0, 35.0));
+        let vertices = state.paint();
+        assert_eq!(vertices[0].pos, [30.0, 30.0]);
+        assert_eq!(vertices[0].color, [0.0, 0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn projection_follows_the_window_size() {
+        let options = WindowOptions {width: 800, height: 600, ..WindowOptions::default()};
+        let corner = options.projection() * Vector4::new(800.0, 600.0, 0.0, 1.0);
+        assert_eq!((corner.x, corner.y), (1.0, -1.0));
+    }
+
+    #[test]
+    fn timer_reports_frame_deltas() {
+        let mut timer = FrameTimer::new(false);
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(timer.tick() >= 0.005);
+        assert_eq!(timer.fps(), 0.0);
+    }
+
+    #[test]
+    fn lays_out_again_on_resize() {
+        let root = ::parse_str("a { width: 50%; height: 10px }").unwrap();
+        let mut state = RenderState::with_root(root, 200, 100);
+        assert_eq!(state.boxes[0].border.width, 100.0);
+
+        state.on_resize(400, 100);
+        assert_eq!(state.boxes[0].border.width, 200.0);
+        assert_eq!(state.projection, ortho_for(400, 100));
+
+        let mut fixed = RenderState::new(state.boxes.clone());
+        fixed.on_resize(800, 100);
+        assert_eq!(fixed.boxes, state.boxes);
+    }
+
+    #[test]
+    fn spins_clockwise_about_the_origin() {
+        let turned = spin_matrix(1.0, [10.0, 10.0]) * Vector4::new(20.0, 10.0, 0.0, 1.0);
+        assert!((turned.x - 10.0).abs() < 1e-4 && (turned.y - 20.0).abs() < 1e-4, "{:?}", turned);
+        assert_eq!(spin_matrix(4.0, [10.0, 10.0]), Matrix4::identity());
+    }
+}